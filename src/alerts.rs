@@ -0,0 +1,488 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    process::Command,
+    str::FromStr,
+    sync::mpsc::{self, Sender},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{sample::Sample, termite::LoggingHandle};
+
+/// The device metrics a [`Threshold`] can watch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    /// GPU die temperature in °C
+    Temperature,
+    /// Memory used, as a percentage of total
+    Memory,
+    /// Core utilisation in %
+    Utilisation,
+    /// Average fan speed in %
+    Fan,
+}
+
+impl Metric {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Temperature => "temperature",
+            Metric::Memory => "memory",
+            Metric::Utilisation => "utilisation",
+            Metric::Fan => "fan",
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "temperature" | "temp" => Ok(Metric::Temperature),
+            "memory" | "mem" => Ok(Metric::Memory),
+            "utilisation" | "utilization" | "util" => Ok(Metric::Utilisation),
+            "fan" => Ok(Metric::Fan),
+            other => Err(format!(
+                "unknown metric '{other}', expected one of: temperature, memory, utilisation, fan"
+            )),
+        }
+    }
+}
+
+/// A user supplied `metric=value` pair, i.e `temperature=85`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    pub metric: Metric,
+    pub value: f64,
+}
+
+impl FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (metric, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected METRIC=VALUE, got '{s}'"))?;
+
+        let metric = metric.parse()?;
+        let value: f64 = value
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid threshold value '{value}': {e}"))?;
+        // Nothing is ever above (or below) NaN, so it'd never fire.
+        if !value.is_finite() {
+            return Err(format!(
+                "invalid threshold value '{value}': must be a number"
+            ));
+        }
+
+        Ok(Threshold { metric, value })
+    }
+}
+
+/// Which side of its [`Threshold`] a metric has just moved to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Crossing {
+    /// The metric rose to, or above the threshold
+    Above,
+    /// The metric has returned below the threshold
+    Below,
+}
+
+impl fmt::Display for Crossing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Crossing::Above => "above",
+            Crossing::Below => "below",
+        })
+    }
+}
+
+/// What gets handed to the configured command/webhook when a [`Threshold`] is crossed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertEvent {
+    pub device: u32,
+    pub uuid: String,
+    pub metric: Metric,
+    pub value: f64,
+    pub threshold: f64,
+    pub state: Crossing,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+/// Remembers which side of each threshold every device was last seen on, so we only emit
+/// an [`AlertEvent`] on a transition rather than on every sample. Devices are told apart by UUID,
+/// as an index can end up on a different GPU after a rescan.
+#[derive(Debug, Default)]
+pub struct AlertWatcher {
+    thresholds: Vec<Threshold>,
+    above: HashMap<(String, usize), bool>,
+}
+
+impl AlertWatcher {
+    pub fn new(thresholds: Vec<Threshold>) -> Self {
+        Self {
+            thresholds,
+            above: HashMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.thresholds.is_empty()
+    }
+
+    /// Feed the watcher the latest readings for `device` (with `uuid`), `read` is only called for the metrics we have thresholds for.
+    pub fn check<F>(&mut self, device: u32, uuid: &str, mut read: F) -> Vec<AlertEvent>
+    where
+        F: FnMut(Metric) -> Option<f64>,
+    {
+        let mut events = vec![];
+
+        for (i, threshold) in self.thresholds.iter().enumerate() {
+            let Some(value) = read(threshold.metric) else {
+                continue;
+            };
+
+            let now_above = value >= threshold.value;
+            let was_above = self
+                .above
+                .insert((uuid.to_string(), i), now_above)
+                .unwrap_or(false);
+
+            if now_above != was_above {
+                events.push(AlertEvent {
+                    device,
+                    uuid: uuid.to_string(),
+                    metric: threshold.metric,
+                    value,
+                    threshold: threshold.value,
+                    state: if now_above {
+                        Crossing::Above
+                    } else {
+                        Crossing::Below
+                    },
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs()),
+                });
+            }
+        }
+
+        events
+    }
+}
+
+/// What to do when an [`AlertEvent`] fires.
+#[derive(Debug, Clone, Default)]
+pub struct AlertActions {
+    /// Run through the shell with `NVTOP_DEVICE`, `NVTOP_UUID`, `NVTOP_METRIC`, `NVTOP_VALUE`, `NVTOP_THRESHOLD` and `NVTOP_STATE` set.
+    pub command: Option<String>,
+    /// POST the event as JSON to this URL.
+    pub webhook: Option<String>,
+    /// Minimum time between two alerts for the same device, metric and direction.
+    pub cooldown: Duration,
+    /// How many more times to try a failed command/webhook before giving up.
+    pub retries: u32,
+    /// Pop up a desktop notification over D-Bus.
+    #[cfg(feature = "dbus")]
+    pub desktop_notify: bool,
+}
+
+impl AlertActions {
+    pub fn is_empty(&self) -> bool {
+        #[cfg(feature = "dbus")]
+        if self.desktop_notify {
+            return false;
+        }
+
+        self.command.is_none() && self.webhook.is_none()
+    }
+}
+
+/// Runs [`AlertActions`] on a background thread so a slow webhook never stalls the UI.
+pub struct AlertHandle {
+    pub sender: Sender<AlertEvent>,
+}
+
+impl AlertHandle {
+    pub fn init(actions: AlertActions, lh: LoggingHandle) -> AlertHandle {
+        let (tx, rx) = mpsc::channel::<AlertEvent>();
+
+        std::thread::spawn(move || {
+            let mut last_fired: HashMap<(String, Metric, Crossing), Instant> = HashMap::new();
+
+            #[cfg(feature = "dbus")]
+            let mut notifier = actions
+                .desktop_notify
+                .then(|| crate::notify::DesktopNotifier::session(actions.cooldown))
+                .and_then(|res| {
+                    res.map_err(|e| lh.error(&format!("Unable to connect to the session bus: {e}")))
+                        .ok()
+                });
+
+            while let Ok(event) = rx.recv() {
+                // Desktop notifications are throttled by device and metric in the notifier itself.
+                #[cfg(feature = "dbus")]
+                if let Some(notifier) = notifier.as_mut() {
+                    if let Err(e) = notifier.notify(&event) {
+                        lh.error(&format!("Desktop notification failed: {e}"));
+                    }
+                }
+
+                let key = (event.uuid.clone(), event.metric, event.state);
+                if let Some(when) = last_fired.get(&key) {
+                    if when.elapsed() < actions.cooldown {
+                        lh.debug(&format!("Alert rate limited: {event:?}"));
+                        continue;
+                    }
+                }
+                last_fired.insert(key, Instant::now());
+
+                lh.info(&format!(
+                    "Alert: GPU[{}] {} {} {} ({:.1})",
+                    event.device, event.metric, event.state, event.threshold, event.value
+                ));
+
+                if let Some(command) = &actions.command {
+                    if let Err(e) = with_retries(actions.retries, || run_command(command, &event)) {
+                        lh.error(&format!("Alert command `{command}` failed: {e}"));
+                    }
+                }
+
+                if let Some(url) = &actions.webhook {
+                    if let Err(e) = with_retries(actions.retries, || post_webhook(url, &event)) {
+                        lh.error(&format!("Alert webhook {url} failed: {e}"));
+                    }
+                }
+            }
+        });
+
+        AlertHandle { sender: tx }
+    }
+
+    /// A dummy, for when no alert actions are configured.
+    pub fn empty() -> AlertHandle {
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+        AlertHandle { sender: tx }
+    }
+
+    pub fn send(&self, event: AlertEvent) {
+        _ = self.sender.send(event);
+    }
+}
+
+/// The [`AlertWatcher`] and the [`AlertHandle`] it reports to, what `app::run` holds on to.
+pub struct Alerter {
+    pub watcher: AlertWatcher,
+    pub handle: AlertHandle,
+}
+
+impl Alerter {
+    pub fn new(thresholds: Vec<Threshold>, actions: AlertActions, lh: &LoggingHandle) -> Self {
+        let handle = if thresholds.is_empty() || actions.is_empty() {
+            AlertHandle::empty()
+        } else {
+            AlertHandle::init(actions, lh.clone())
+        };
+
+        Self {
+            watcher: AlertWatcher::new(thresholds),
+            handle,
+        }
+    }
+
+    /// Check the latest `sample` from `device` (with `uuid`) and dispatch any threshold crossings.
+    pub fn check(&mut self, device: u32, uuid: &str, sample: &Sample) {
+        if self.watcher.is_empty() {
+            return;
+        }
+
+        self.watcher
+            .check(device, uuid, |metric| sample.metric(metric))
+            .into_iter()
+            .for_each(|event| self.handle.send(event));
+    }
+}
+
+fn with_retries<F>(retries: u32, mut f: F) -> Result<(), String>
+where
+    F: FnMut() -> Result<(), String>,
+{
+    let mut attempt = 0;
+    loop {
+        match f() {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= retries => return Err(e),
+            Err(_) => {
+                // Backs off to at most 16s between attempts.
+                std::thread::sleep(Duration::from_millis(250) * 2u32.pow(attempt.min(6)));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+fn run_command(command: &str, event: &AlertEvent) -> Result<(), String> {
+    #[cfg(not(target_os = "windows"))]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+
+    let status = cmd
+        .env("NVTOP_DEVICE", event.device.to_string())
+        .env("NVTOP_UUID", &event.uuid)
+        .env("NVTOP_METRIC", event.metric.name())
+        .env("NVTOP_VALUE", format!("{:.1}", event.value))
+        .env("NVTOP_THRESHOLD", event.threshold.to_string())
+        .env("NVTOP_STATE", event.state.to_string())
+        .status()
+        .map_err(|e| e.to_string())?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("exited with {status}"))
+    }
+}
+
+fn post_webhook(url: &str, event: &AlertEvent) -> Result<(), String> {
+    ureq::post(url)
+        .timeout(Duration::from_secs(10))
+        .send_json(event)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    fn event() -> AlertEvent {
+        AlertEvent {
+            device: 1,
+            uuid: "GPU-5ad2b1e8-0d3c-4a25-8a4b-0c6b1d5e6f70".into(),
+            metric: Metric::Temperature,
+            value: 87.0,
+            threshold: 85.0,
+            state: Crossing::Above,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn parse_threshold() {
+        assert_eq!(
+            "temp=85".parse::<Threshold>().unwrap(),
+            Threshold {
+                metric: Metric::Temperature,
+                value: 85.0
+            }
+        );
+        assert!("memory".parse::<Threshold>().is_err());
+        assert!("voltage=3".parse::<Threshold>().is_err());
+        assert!("temp=NaN".parse::<Threshold>().is_err());
+        assert!("fan=inf".parse::<Threshold>().is_err());
+    }
+
+    #[test]
+    fn watcher_only_fires_on_transitions() {
+        let mut watcher = AlertWatcher::new(vec!["temperature=80".parse().unwrap()]);
+
+        assert!(watcher.check(0, "GPU-a", |_| Some(50.0)).is_empty());
+        let events = watcher.check(0, "GPU-a", |_| Some(81.0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].state, Crossing::Above);
+        assert!(watcher.check(0, "GPU-a", |_| Some(90.0)).is_empty());
+
+        // Other devices are tracked separately.
+        assert_eq!(watcher.check(1, "GPU-b", |_| Some(85.0)).len(), 1);
+
+        let events = watcher.check(0, "GPU-a", |_| Some(79.0));
+        assert_eq!(events[0].state, Crossing::Below);
+
+        // A different GPU that's ended up at index 0 after a rescan starts afresh, it doesn't get a
+        // "below" for a crossing it never made.
+        assert!(watcher.check(0, "GPU-c", |_| Some(20.0)).is_empty());
+        assert!(watcher.check(1, "GPU-a", |_| Some(20.0)).is_empty());
+    }
+
+    #[test]
+    fn webhook_posts_json_and_retries() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let mut bodies = vec![];
+            for status in ["500 Internal Server Error", "200 OK"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut content_length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = len.trim().parse().unwrap();
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+
+                write!(stream, "HTTP/1.1 {status}\r\nContent-Length: 0\r\n\r\n").unwrap();
+            }
+            bodies
+        });
+
+        with_retries(1, || post_webhook(&url, &event())).unwrap();
+
+        let bodies = server.join().unwrap();
+        assert_eq!(bodies.len(), 2);
+        let json: serde_json::Value = serde_json::from_str(&bodies[1]).unwrap();
+        assert_eq!(json["metric"], "temperature");
+        assert_eq!(json["state"], "above");
+        assert_eq!(json["device"], 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_gets_event_in_env() {
+        let out = std::env::temp_dir().join(format!("nvtop-alert-{}", std::process::id()));
+        let command = format!(
+            "echo \"$NVTOP_DEVICE $NVTOP_METRIC $NVTOP_VALUE $NVTOP_STATE\" > {}",
+            out.display()
+        );
+
+        run_command(&command, &event()).unwrap();
+
+        let written = std::fs::read_to_string(&out).unwrap();
+        _ = std::fs::remove_file(&out);
+        assert_eq!(written.trim(), "1 temperature 87.0 above");
+        assert!(run_command("exit 3", &event()).is_err());
+    }
+}
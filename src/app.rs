@@ -1,7 +1,5 @@
-use nvml_wrapper::enum_wrappers::device::{Clock, ClockId};
 use nvml_wrapper::error::NvmlError;
 use nvml_wrapper::struct_wrappers::device::PciInfo;
-use nvml_wrapper::{enum_wrappers::device::TemperatureSensor, struct_wrappers::device::MemoryInfo};
 
 use ratatui::symbols::DOT;
 use ratatui::{prelude::*, widgets::*};
@@ -10,37 +8,192 @@ use ratatui::{
     widgets::Paragraph,
 };
 
-use std::time::Duration;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use crate::errors::NvTopError;
-use crate::stylers::calculate_severity;
+use crate::alerts::{Alerter, Metric};
+use crate::compact;
+use crate::config::{Config, Panel};
+use crate::devices::same_bus_id;
+use crate::health::{sample_devices, Health};
+use crate::keymap::{Action, FixedKey, Keymap};
+use crate::layout::Layouts;
+use crate::mig::sample_instances;
+use crate::nvlink::{self, Links, LinksSample};
+use crate::rescan::{device_uuids, needs_rescan, rescan};
+use crate::sample::{History, Playback, Sample};
+use crate::settings::SettingsMenu;
+use crate::terminal::{Signals, TerminalGuard};
 use crate::termite::LoggingHandle;
+use crate::theme::Theme;
+use crate::topo::Topology;
+use crate::vgpu::VgpuInstance;
+use crate::zoom::draw_zoomed;
 use crate::{errors, gpu::GpuInfo};
 pub type Frame<'a> = ratatui::Frame<'a, CrosstermBackend<std::io::Stderr>>;
 
 pub fn run(
     nvml: nvml_wrapper::Nvml,
-    delay: Duration,
+    mut cfg: Config,
+    mut file_cfg: Config,
+    config_path: Option<PathBuf>,
     lh: &LoggingHandle,
+    alerter: &mut Alerter,
 ) -> anyhow::Result<(), errors::NvTopError> {
-    crossterm::terminal::enable_raw_mode()?;
-    crossterm::execute!(std::io::stderr(), crossterm::terminal::EnterAlternateScreen)?;
+    let signals = Signals::register()?;
+    let guard = TerminalGuard::enter(cfg.mouse)?;
 
     let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stderr()))?;
     lh.debug("crossterm initialisation successful");
 
-    let mut gpu_list = crate::gpu::try_init_gpus(&nvml, lh)?;
+    let mut gpu_list = crate::gpu::try_init_gpus(&nvml, &cfg.device_filters(), lh)?;
 
+    let theme = Theme::from_config(&cfg);
+    let mut settings = SettingsMenu::default();
+    let mut view = View::Device;
+    let mut show_help = false;
     let mut selected_gpu: usize = 0;
-    let mut have_fans: bool = gpu_list
-        .iter()
-        .any(|gpu| gpu.inner.num_fans().map_or(0, |fc| fc) != 0);
-
-    lh.debug(&format!("GPU has fans = {}", have_fans));
+    let mut index_entry = IndexEntry::default();
+    let mut focused: Option<Panel> = None;
+    let mut zoomed: Option<Panel> = None;
+    let mut layouts = Layouts::from_config(&cfg);
+    let mut hits = HitAreas::default();
+    let mut histories = new_histories(&gpu_list, &cfg);
+    let mut health = vec![Health::default(); gpu_list.len()];
+    let mut playback = Playback::default();
+    let mut last_sample: Option<Instant> = None;
+    let mut last_rescan = Instant::now();
+    let mut info_scroll: u16 = 0;
+    // Where the panels were last laid out, which decides which layout is on screen.
+    let mut layout_area = Rect::default();
+    // Only the latest, MIG instances don't get a history.
+    let mut instance_samples = HashMap::new();
+    // By UUID, only kept up to date while the NVLink view is open.
+    let mut links: HashMap<String, Links> = HashMap::new();
+    // Read when the view's opened, and again if devices come or go.
+    let mut topology: Option<Topology> = None;
+    // By UUID, only read for the selected device while the vGPU view is open.
+    let mut vgpus: HashMap<String, Vec<VgpuInstance>> = HashMap::new();
+    let mut notice: Option<(String, Instant)> = None;
 
     loop {
-        _ = terminal.draw(|f| {
+        if signals.quit_requested() {
+            break;
+        }
+        #[cfg(unix)]
+        if signals.take_suspend() {
+            guard.suspend()?;
+            terminal.clear()?;
+        }
+        if signals.take_resumed() {
+            guard.resume()?;
+            terminal.clear()?;
+        }
+
+        if cfg.rescan != 0 && last_rescan.elapsed() >= Duration::from_secs(cfg.rescan) {
+            last_rescan = Instant::now();
+            match device_uuids(&nvml, &cfg.device_filters()) {
+                Ok(found)
+                    if needs_rescan(
+                        gpu_list.iter().map(|g| g.uuid.as_str()).zip(&health),
+                        &found,
+                    ) =>
+                {
+                    if let Some(text) = rescan_devices(
+                        &nvml,
+                        &cfg,
+                        &mut gpu_list,
+                        &mut health,
+                        &mut histories,
+                        &mut selected_gpu,
+                        lh,
+                    ) {
+                        notice = Some((text, Instant::now()));
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => lh.debug(&format!("Failed to poll for devices: {e}")),
+            }
+        }
+
+        // Sampling carries on while the display is frozen, so there's history to step through.
+        let delay = Duration::from_millis(cfg.delay);
+        if !matches!(last_sample, Some(last) if last.elapsed() < delay) {
+            let samples = sample_devices(&nvml, &mut gpu_list, &mut health, lh);
+            for (((gpu, health), history), sample) in gpu_list
+                .iter()
+                .zip(&health)
+                .zip(&mut histories)
+                .zip(samples)
+            {
+                if health.is_healthy() {
+                    alerter.check(gpu.index, &gpu.uuid, &sample);
+                }
+                history.push(sample);
+            }
+            instance_samples = sample_instances(&gpu_list, &health);
+            if view == View::Links {
+                if !matches!(&topology, Some(t) if t.describes(&gpu_list)) {
+                    topology = Some(Topology::read(&gpu_list, Path::new("/sys")));
+                }
+                for (gpu, _) in gpu_list.iter().zip(&health).filter(|(_, h)| h.is_healthy()) {
+                    let sample = LinksSample::take(gpu);
+                    match links.get_mut(&gpu.uuid) {
+                        Some(device_links) => device_links.push(sample),
+                        None => {
+                            links.insert(gpu.uuid.clone(), Links::new(sample));
+                        }
+                    }
+                }
+            }
             let gpu = &gpu_list[selected_gpu];
+            let host = matches!(gpu.details.virtualization_mode, Some(mode) if mode.is_host());
+            if view == View::Vgpus && host && health[selected_gpu].is_healthy() {
+                match VgpuInstance::active(gpu, &gpu.details.vgpu_types) {
+                    Ok(instances) => {
+                        vgpus.insert(gpu.uuid.clone(), instances);
+                    }
+                    Err(e) => lh.debug(&format!("Failed to read vGPUs on [{}]: {e}", gpu.index)),
+                }
+            }
+            last_sample = Some(Instant::now());
+        }
+
+        terminal.draw(|f| {
+            let cfg = &cfg;
+            hits = HitAreas::default();
+            let gpu = &gpu_list[selected_gpu];
+            let samples = histories
+                .iter()
+                .map(|history| playback.sample(history))
+                .collect::<Vec<_>>();
+
+            let [min_width, min_height] = cfg.layout.table_below;
+            if f.size().width < min_width || f.size().height < min_height {
+                hits.rows = draw_tiny(f, &gpu_list, &health, &samples, selected_gpu, cfg, &theme);
+                if let Playback::Frozen(_) = playback {
+                    let title = border_title(playback, &histories[selected_gpu]);
+                    f.render_widget(
+                        Paragraph::new(title).style(theme.highlight_style()),
+                        Rect {
+                            height: 1,
+                            ..f.size()
+                        },
+                    );
+                }
+                return;
+            }
+
+            // Typing an index is left to the help, how many digits it takes depends on the devices.
+            let footer = cfg.keys.footer();
+            let footer = match &notice {
+                Some((text, shown)) if shown.elapsed() < NOTICE_FOR => {
+                    Paragraph::new(text.as_str()).style(theme.accent_style())
+                }
+                _ => Paragraph::new(footer),
+            };
 
             // draw tab bar if more than one device is connected
             let mid_area = if gpu_list.len() == 1 {
@@ -49,14 +202,7 @@ pub fn run(
                     .constraints([Constraint::Min(0), Constraint::Length(1)])
                     .split(f.size());
 
-                #[cfg(target_os = "linux")]
-                f.render_widget(
-                    Paragraph::new("q to quit, p to rescan devices").alignment(Alignment::Right),
-                    layout[1],
-                );
-
-                #[cfg(target_os = "windows")]
-                f.render_widget(Paragraph::new("q to quit"), layout[1]);
+                f.render_widget(footer.alignment(Alignment::Right), layout[1]);
 
                 layout[0]
             } else {
@@ -69,239 +215,1213 @@ pub fn run(
                     ])
                     .split(f.size());
 
-                f.render_widget(
-                    Tabs::new(
-                        gpu_list
-                            .iter()
-                            .map(|gpu| format!("[{}] {}", gpu.index, gpu.card_type))
-                            .collect(),
-                    )
-                    .select(selected_gpu)
-                    .style(Style::default().fg(Color::Green))
-                    .highlight_style(Style::default().fg(Color::Green).bold())
-                    .divider(DOT),
-                    layout[0],
-                );
-
-                f.render_widget(
-                    Paragraph::new("q to quit, p to rescan devices, fn keys to switch devices"),
-                    layout[2],
-                );
+                hits.tab_bar = Some(layout[0]);
+                hits.tabs = draw_tabs(f, layout[0], &gpu_list, &health, selected_gpu, &theme);
+
+                f.render_widget(footer, layout[2]);
 
                 layout[1]
             };
 
             // Outermost Block, which draws the green border aound the whole UI.
             let block = Block::default()
-                .title("NVTOP")
+                .title(border_title(playback, &histories[selected_gpu]))
                 .title_position(block::Position::Top)
                 .title_alignment(Alignment::Center)
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Green))
+                .border_style(theme.border_style())
                 .border_type(BorderType::Rounded)
                 .style(Style::default());
             f.render_widget(block, mid_area);
+            layout_area = mid_area;
 
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints(vec![Constraint::Percentage(70), Constraint::Percentage(30)])
-                .margin(1)
-                .split(f.size());
-
-            {
-                let chunks = Layout::default()
-                    .constraints(vec![
-                        Constraint::Percentage(60),
-                        Constraint::Percentage(20),
-                        Constraint::Percentage(20),
-                    ])
-                    .margin(1)
-                    .split(chunks[0]);
+            match view {
+                View::Device => match zoomed {
+                    Some(panel) => {
+                        let area = inner(mid_area);
+                        hits.panels = vec![(panel, area)];
+                        draw_zoomed(
+                            f,
+                            area,
+                            panel,
+                            gpu,
+                            &histories[selected_gpu],
+                            playback,
+                            &mut info_scroll,
+                            cfg,
+                            &theme,
+                        );
+                    }
+                    None => {
+                        let (name, layout) = layouts.for_area(mid_area);
+                        let panels = gpu.capabilities.visible_panels(&cfg.panels);
+                        hits.panels = layout.place(inner(mid_area), &panels);
+                        if name != Layouts::DEFAULT {
+                            f.render_widget(
+                                Block::default().title(
+                                    block::Title::from(format!(" {name} "))
+                                        .position(block::Position::Bottom)
+                                        .alignment(Alignment::Right),
+                                ),
+                                mid_area,
+                            );
+                        }
+                        for &(panel, area) in &hits.panels {
+                            if panel == Panel::Misc {
+                                info_scroll = draw_info(f, area, gpu, info_scroll, cfg, &theme);
+                            } else if let Some(sample) = samples[selected_gpu] {
+                                draw_panel(
+                                    f,
+                                    panel,
+                                    area,
+                                    gpu,
+                                    sample,
+                                    &mut info_scroll,
+                                    cfg,
+                                    &theme,
+                                );
+                            }
+                            if focused == Some(panel) {
+                                highlight_border(f, area, theme.accent_style());
+                            }
+                        }
+                    }
+                },
+                View::Table => {
+                    let (table, owners) = draw_device_table(
+                        &gpu_list,
+                        &health,
+                        &samples,
+                        &instance_samples,
+                        cfg,
+                        &theme,
+                    );
+                    let mut state = TableState::default();
+                    state.select(owners.iter().position(|&owner| owner == selected_gpu));
+                    let area = inner(mid_area);
+                    f.render_stateful_widget(table, area, &mut state);
 
-                // Core:
-                let core_gauge = draw_core_utilisation(gpu);
-                f.render_widget(core_gauge, chunks[0]);
+                    // Below the header, one line per row from wherever the table scrolled to,
+                    // MIG instances select their parent.
+                    hits.table = Some(area);
+                    hits.rows = owners[state.offset().min(owners.len())..]
+                        .iter()
+                        .zip(area.y + 1..area.bottom())
+                        .map(|(&index, y)| {
+                            (
+                                index,
+                                Rect {
+                                    y,
+                                    height: 1,
+                                    ..area
+                                },
+                            )
+                        })
+                        .collect();
+                }
+                View::Links => draw_links(
+                    f,
+                    inner(mid_area),
+                    &gpu_list,
+                    selected_gpu,
+                    topology.as_ref(),
+                    &links,
+                    cfg,
+                    &theme,
+                ),
+                View::Vgpus => {
+                    let gpu = &gpu_list[selected_gpu];
+                    let instances = vgpus.get(&gpu.uuid);
+                    draw_vgpus(f, inner(mid_area), gpu, instances, cfg, &theme)
+                }
+            }
 
-                // Core Clock:
-                let core_gauge = draw_core_clock(gpu).unwrap();
-                f.render_widget(core_gauge, chunks[1]);
+            if view == View::Device {
+                if let Some(lost) = health[selected_gpu].describe() {
+                    draw_lost(f, inner(mid_area), &lost, &cfg.keys, &theme);
+                }
+            }
 
-                // Misc:
-                let paragraph = draw_misc(gpu);
-                f.render_widget(paragraph, chunks[2]);
+            if show_help {
+                draw_help(f, f.size(), &cfg.keys, &theme);
             }
 
-            {
-                let chunks = Layout::default()
-                    .constraints([
-                        Constraint::Percentage(33),
-                        Constraint::Percentage(33),
-                        Constraint::Percentage(33),
-                    ])
-                    .direction(Direction::Vertical)
-                    .margin(1)
-                    .split(chunks[1]);
+            if settings.open {
+                settings.draw(f, f.size(), cfg, &theme);
+            }
+        })?;
 
-                // Memory:
-                let mem_usage_gauge = draw_memory_usage(gpu);
-                f.render_widget(mem_usage_gauge, chunks[0]);
+        let until_next_sample =
+            last_sample.map_or(Duration::ZERO, |last| delay.saturating_sub(last.elapsed()));
+        if crossterm::event::poll(until_next_sample)? {
+            use crossterm::event::{Event, KeyEventKind, MouseButton, MouseEventKind};
 
-                // Temp:
-                let temp_gauge = draw_gpu_die_temp(gpu);
-                f.render_widget(temp_gauge, chunks[1]);
+            match crossterm::event::read()? {
+                Event::Key(key) => {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
 
-                // Fan speed:
-                if have_fans {
-                    let gauge = draw_fan_speed(gpu);
-                    f.render_widget(gauge, chunks[2]);
-                }
-            }
-        })?;
+                    if settings.open {
+                        settings.handle_key(key, &mut cfg, &mut file_cfg, config_path.as_deref());
+                        continue;
+                    }
 
-        if crossterm::event::poll(std::time::Duration::from_millis(250))? {
-            if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
-                use crossterm::event::KeyCode;
+                    let fixed = FixedKey::from_code(key.code);
+                    if show_help && fixed == Some(FixedKey::Escape) {
+                        show_help = false;
+                        continue;
+                    }
 
-                match key.code {
-                    KeyCode::Char('q') => break,
-                    KeyCode::F(n) if (1..=gpu_list.len()).contains(&n.into()) => {
-                        selected_gpu = usize::from(n - 1)
+                    if zoomed.is_some() && fixed == Some(FixedKey::Escape) {
+                        zoomed = None;
+                        continue;
                     }
 
-                    #[cfg(target_os = "linux")]
-                    KeyCode::Char('p') => {
-                        // re-scan pci tree to let driver discover new devices (only works as sudo)
-                        match nvml.discover_gpus(PciInfo {
-                            bus: 0,
-                            bus_id: "".into(),
-                            device: 0,
-                            domain: 0,
-                            pci_device_id: 0,
-                            pci_sub_system_id: Some(0),
-                        }) {
-                            Ok(()) => {
-                                have_fans = gpu_list
+                    match cfg.keys.action_for(&key) {
+                        Some(Action::Quit) => break,
+                        Some(Action::Suspend) => signals.raise_suspend(),
+                        Some(Action::Help) => show_help = !show_help,
+                        Some(Action::Settings) => settings.toggle(),
+                        Some(Action::Pause) => playback.toggle(&histories[selected_gpu]),
+                        Some(Action::StepBack) => playback.step(&histories[selected_gpu], true),
+                        Some(Action::StepForward) => playback.step(&histories[selected_gpu], false),
+                        Some(Action::FocusNext) => {
+                            let visible = panels_on_screen(
+                                &layouts,
+                                layout_area,
+                                &gpu_list[selected_gpu],
+                                &cfg,
+                            );
+                            let next = focused
+                                .and_then(|panel| visible.iter().position(|p| *p == panel))
+                                .map_or(0, |i| (i + 1) % visible.len().max(1));
+                            focused = visible.get(next).copied();
+                            if zoomed.is_some() {
+                                zoomed = focused;
+                            }
+                        }
+                        Some(Action::Zoom) => {
+                            zoomed = match zoomed {
+                                Some(_) => None,
+                                None => {
+                                    let visible = panels_on_screen(
+                                        &layouts,
+                                        layout_area,
+                                        &gpu_list[selected_gpu],
+                                        &cfg,
+                                    );
+                                    focused
+                                        .filter(|panel| visible.contains(panel))
+                                        .or_else(|| visible.first().copied())
+                                }
+                            };
+                            focused = focused.or(zoomed);
+                        }
+                        Some(Action::NextLayout) => layouts.next(),
+                        Some(Action::ScrollUp) => info_scroll = info_scroll.saturating_sub(1),
+                        Some(Action::ScrollDown) => info_scroll = info_scroll.saturating_add(1),
+                        Some(Action::ToggleView) => {
+                            view = match view {
+                                View::Table => View::Device,
+                                _ => View::Table,
+                            }
+                        }
+                        Some(Action::ToggleLinks) => {
+                            view = match view {
+                                View::Links => View::Device,
+                                _ => {
+                                    // Read the links straight away rather than on the next tick.
+                                    last_sample = None;
+                                    topology = None;
+                                    View::Links
+                                }
+                            }
+                        }
+                        Some(Action::ToggleVgpus) => {
+                            view = match view {
+                                View::Vgpus => View::Device,
+                                _ => {
+                                    last_sample = None;
+                                    View::Vgpus
+                                }
+                            }
+                        }
+                        Some(Action::NextDevice) => {
+                            selected_gpu = (selected_gpu + 1) % gpu_list.len()
+                        }
+                        Some(Action::PrevDevice) => {
+                            selected_gpu = (selected_gpu + gpu_list.len() - 1) % gpu_list.len()
+                        }
+                        Some(Action::Rescan) => {
+                            // re-scan pci tree to let driver discover new devices (only works as sudo)
+                            #[cfg(target_os = "linux")]
+                            match nvml.discover_gpus(PciInfo {
+                                bus: 0,
+                                bus_id: "".into(),
+                                device: 0,
+                                domain: 0,
+                                pci_device_id: 0,
+                                pci_sub_system_id: Some(0),
+                            }) {
+                                Ok(()) => lh.debug("Re-scanned PCI tree"),
+                                Err(e @ (NvmlError::OperatingSystem | NvmlError::NoPermission)) => {
+                                    lh.debug(&format!("Failed to re-scan PCI tree: {e}"));
+                                }
+                                Err(e) => lh.error(&format!("Failed to re-scan PCI tree: {e}")),
+                            }
+                            notice = rescan_devices(
+                                &nvml,
+                                &cfg,
+                                &mut gpu_list,
+                                &mut health,
+                                &mut histories,
+                                &mut selected_gpu,
+                                lh,
+                            )
+                            .or_else(|| Some("No change in devices".into()))
+                            .map(|text| (text, Instant::now()));
+                            last_rescan = Instant::now();
+                        }
+                        None => match fixed {
+                            // F1 is device 0, by the index on its tab.
+                            Some(FixedKey::Function(n)) => {
+                                if let Some(position) = gpu_list
                                     .iter()
-                                    .any(|gpu| gpu.inner.num_fans().map_or(0, |fc| fc) != 0);
-
-                                lh.debug(&format!("GPU has fans = {}", have_fans));
-                                lh.debug("Re-scanned PCI tree");
+                                    .position(|gpu| gpu.index + 1 == u32::from(n))
+                                {
+                                    selected_gpu = position
+                                }
+                            }
+                            Some(FixedKey::Digit(c)) => {
+                                let indices =
+                                    gpu_list.iter().map(|gpu| gpu.index).collect::<Vec<_>>();
+                                if let Some(position) = index_entry.push(c, &indices) {
+                                    selected_gpu = position;
+                                }
                             }
-                            Err(e @ (NvmlError::OperatingSystem | NvmlError::NoPermission)) => {
-                                lh.debug(&format!("Failed to re-scan PCI tree: {e}"));
+                            _ => {}
+                        },
+                    }
+                }
+                Event::Mouse(mouse) if cfg.mouse => {
+                    let (column, row) = (mouse.column, mouse.row);
+                    let over = |area: Option<Rect>| area.is_some_and(|area| hit(area, column, row));
+
+                    match mouse.kind {
+                        MouseEventKind::Down(MouseButton::Left) if !settings.open && !show_help => {
+                            if let Some(&(index, _)) = hits
+                                .tabs
+                                .iter()
+                                .chain(&hits.rows)
+                                .find(|(_, area)| hit(*area, column, row))
+                            {
+                                selected_gpu = index;
+                            } else if let Some(&(panel, _)) =
+                                hits.panels.iter().find(|(_, area)| hit(*area, column, row))
+                            {
+                                // A second click on the same panel zooms in (or back out).
+                                if focused == Some(panel) {
+                                    zoomed = match zoomed {
+                                        Some(_) => None,
+                                        None => Some(panel),
+                                    };
+                                }
+                                focused = Some(panel);
                             }
-                            Err(e) => return Err(e.into()),
                         }
-                        // re-scan for devices
-                        gpu_list = crate::gpu::try_init_gpus(&nvml, lh)?;
-                        if selected_gpu >= gpu_list.len() {
-                            selected_gpu = 0;
+                        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                            let down = mouse.kind == MouseEventKind::ScrollDown;
+                            let over_info = hits
+                                .panels
+                                .iter()
+                                .any(|&(panel, area)| panel == Panel::Misc && over(Some(area)));
+                            if settings.open {
+                                settings.scroll(down);
+                            } else if over_info {
+                                info_scroll = match down {
+                                    true => info_scroll.saturating_add(1),
+                                    false => info_scroll.saturating_sub(1),
+                                };
+                            } else if over(hits.tab_bar) || over(hits.table) {
+                                selected_gpu = match down {
+                                    true => (selected_gpu + 1).min(gpu_list.len() - 1),
+                                    false => selected_gpu.saturating_sub(1),
+                                };
+                            }
                         }
+                        _ => {}
                     }
-                    _ => {}
                 }
+                _ => {}
             }
         }
+    }
+
+    Ok(())
+}
+
+/// What's drawn inside the main border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum View {
+    /// The gauges for the selected device.
+    Device,
+    /// One row per device.
+    Table,
+    /// Which devices are linked to which, and the selected device's NVLinks.
+    Links,
+    /// The vGPUs running on the selected device.
+    Vgpus,
+}
+
+/// How long a notice replaces the footer for.
+const NOTICE_FOR: Duration = Duration::from_secs(10);
+
+/// [`rescan`] while keeping hold of the selected device by UUID, as indices may have moved.
+/// Failures are logged and returned as the notice, the devices we had are kept.
+fn rescan_devices<'n>(
+    nvml: &'n nvml_wrapper::Nvml,
+    cfg: &Config,
+    gpu_list: &mut Vec<GpuInfo<'n>>,
+    health: &mut Vec<Health>,
+    histories: &mut Vec<History>,
+    selected: &mut usize,
+    lh: &LoggingHandle,
+) -> Option<String> {
+    let selected_uuid = gpu_list[*selected].uuid.clone();
+    let notice = match rescan(nvml, cfg, gpu_list, health, histories, lh) {
+        Ok(notice) => notice,
+        Err(e) => {
+            lh.error(&format!("Rescan failed: {e}"));
+            Some(format!("Rescan failed: {e}"))
+        }
+    };
+    *selected = gpu_list
+        .iter()
+        .position(|gpu| gpu.uuid == selected_uuid)
+        .unwrap_or(0);
+
+    notice
+}
+
+fn new_histories(gpu_list: &[GpuInfo], cfg: &Config) -> Vec<History> {
+    gpu_list.iter().map(|_| History::new(cfg.history)).collect()
+}
 
-        // primitive rate limiting.
-        std::thread::sleep(delay);
+/// `NVTOP`, or where we are in the history while frozen.
+fn border_title(playback: Playback, history: &History) -> String {
+    match (playback, playback.sample(history), history.newest()) {
+        (Playback::Frozen(n), Some(sample), Some(newest)) => {
+            let behind = newest - n.clamp(history.oldest(), newest);
+            format!(
+                "NVTOP [FROZEN {:.1}s ago, {behind} behind live]",
+                sample.taken.elapsed().as_secs_f64()
+            )
+        }
+        _ => "NVTOP".into(),
     }
+}
 
-    crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen)?;
-    crossterm::terminal::disable_raw_mode()?;
+/// Picks a device by its index (the one on its tab) as it's typed, so `1` then `2` selects device
+/// 12 when there is one.
+#[derive(Debug, Default)]
+struct IndexEntry {
+    typed: String,
+    last: Option<Instant>,
+}
 
-    Ok(())
+impl IndexEntry {
+    /// Digits typed further apart than this start a new index.
+    const TIMEOUT: Duration = Duration::from_millis(1000);
+
+    /// Add `digit`, returning the position in `indices` (the device list's indices, which the
+    /// device filters can leave gaps in) of the device typed so far, if there is one.
+    fn push(&mut self, digit: char, indices: &[u32]) -> Option<usize> {
+        let now = Instant::now();
+        if !matches!(self.last, Some(last) if now - last <= Self::TIMEOUT) {
+            self.typed.clear();
+        }
+        self.last = Some(now);
+        self.typed.push(digit);
+
+        let starting_with = |typed: &str| {
+            indices
+                .iter()
+                .map(u32::to_string)
+                .filter(|index| index.starts_with(typed))
+                .collect::<Vec<_>>()
+        };
+
+        let mut candidates = starting_with(&self.typed);
+        // Typing on into something that isn't a device starts again from this digit.
+        if candidates.is_empty() {
+            self.typed = digit.to_string();
+            candidates = starting_with(&self.typed);
+        }
+        let position = indices
+            .iter()
+            .position(|index| index.to_string() == self.typed);
+
+        // Nothing more can be typed after this, so the next digit starts a new index.
+        if !candidates
+            .iter()
+            .any(|index| index.len() > self.typed.len())
+        {
+            self.typed.clear();
+        }
+
+        position
+    }
+}
+
+/// The device tabs, scrolled so the selected one is always on screen, returns where each visible tab ended up.
+fn draw_tabs(
+    f: &mut Frame,
+    area: Rect,
+    gpu_list: &[GpuInfo],
+    health: &[Health],
+    selected: usize,
+    theme: &Theme,
+) -> Vec<(usize, Rect)> {
+    // MIG instances are listed after their parent, i.e `[0] Tesla ├ 1/0 ├ 2/0`.
+    let titles = gpu_list
+        .iter()
+        .map(|gpu| {
+            let mut title = format!("[{}] {}", gpu.index, gpu.card_type);
+            for instance in &gpu.mig {
+                title.push_str(&format!(" ├ {}", instance.id()));
+            }
+            title
+        })
+        .collect::<Vec<_>>();
+    let tabs = titles
+        .iter()
+        .zip(health)
+        .map(|(title, health)| match health.is_healthy() {
+            true => Line::from(title.clone()),
+            false => Line::styled(title.clone(), theme.lost_style()),
+        })
+        .collect::<Vec<_>>();
+    // Each tab is padded by a space either side and followed by the divider.
+    let widths = titles
+        .iter()
+        .map(|title| title.chars().count() + 3)
+        .collect::<Vec<_>>();
+
+    let mut tabs_area = area;
+    let mut window = 0..titles.len();
+    if widths.iter().sum::<usize>() > usize::from(area.width) {
+        // Leave room for the arrows showing there's more off screen.
+        tabs_area = Rect {
+            x: area.x + 2,
+            width: area.width.saturating_sub(4),
+            ..area
+        };
+        window = tab_window(&widths, selected, tabs_area.width.into());
+
+        let more = |hidden: bool, arrow: &'static str| if hidden { arrow } else { "" };
+        f.render_widget(
+            Paragraph::new(more(window.start > 0, "‹")).style(theme.accent_style()),
+            Rect { width: 2, ..area },
+        );
+        f.render_widget(
+            Paragraph::new(more(window.end < titles.len(), "›"))
+                .style(theme.accent_style())
+                .alignment(Alignment::Right),
+            Rect {
+                x: area.right().saturating_sub(2),
+                width: 2.min(area.width),
+                ..area
+            },
+        );
+    }
+
+    f.render_widget(
+        Tabs::new(tabs[window.clone()].to_vec())
+            .select(selected - window.start)
+            .style(theme.border_style())
+            .highlight_style(theme.border_style().bold())
+            .divider(DOT),
+        tabs_area,
+    );
+
+    let mut x = tabs_area.x;
+    window
+        .map(|index| {
+            let width = widths[index] as u16;
+            let tab = Rect {
+                x,
+                width: (width - 1).min(tabs_area.right().saturating_sub(x)),
+                ..tabs_area
+            };
+            x = x.saturating_add(width);
+            (index, tab)
+        })
+        .collect()
+}
+
+/// The run of tabs, given their `widths`, that fits in `available` columns and includes `selected`.
+fn tab_window(widths: &[usize], selected: usize, available: usize) -> Range<usize> {
+    // Start as far left as possible while still reaching `selected`...
+    let mut start = selected;
+    let mut used = widths[selected];
+    while start > 0 && used + widths[start - 1] <= available {
+        start -= 1;
+        used += widths[start];
+    }
+
+    // ...then fill whatever room is left to the right.
+    let mut end = selected + 1;
+    while end < widths.len() && used + widths[end] <= available {
+        used += widths[end];
+        end += 1;
+    }
+
+    start..end
+}
+
+/// The panels of the layout drawn in `area` (which may be the compact fallback rather than the
+/// current one) that `gpu` shows, in order.
+fn panels_on_screen(layouts: &Layouts, area: Rect, gpu: &GpuInfo, cfg: &Config) -> Vec<Panel> {
+    let panels = gpu.capabilities.visible_panels(&cfg.panels);
+    layouts
+        .for_area(area)
+        .1
+        .widgets()
+        .into_iter()
+        .filter(|panel| panels.is_visible(*panel))
+        .collect()
+}
+
+/// Where things were last drawn, for working out what the mouse is over.
+#[derive(Debug, Default)]
+struct HitAreas {
+    tab_bar: Option<Rect>,
+    /// Each device's tab, by index.
+    tabs: Vec<(usize, Rect)>,
+    panels: Vec<(Panel, Rect)>,
+    table: Option<Rect>,
+    /// Each device's row in the table, by index.
+    rows: Vec<(usize, Rect)>,
+}
+
+fn hit(area: Rect, column: u16, row: u16) -> bool {
+    (area.left()..area.right()).contains(&column) && (area.top()..area.bottom()).contains(&row)
+}
+
+/// Restyle the border around `area`, leaving whatever was drawn inside alone.
+fn highlight_border(f: &mut Frame, area: Rect, style: Style) {
+    f.render_widget(BorderHighlight(style), area);
+}
+
+/// Only touches the outermost cells of the area it's rendered to.
+struct BorderHighlight(Style);
+
+impl Widget for BorderHighlight {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let edges = [
+            Rect {
+                height: 1.min(area.height),
+                ..area
+            },
+            Rect {
+                y: area.bottom().saturating_sub(1),
+                height: 1.min(area.height),
+                ..area
+            },
+            Rect {
+                width: 1.min(area.width),
+                ..area
+            },
+            Rect {
+                x: area.right().saturating_sub(1),
+                width: 1.min(area.width),
+                ..area
+            },
+        ];
+        for edge in edges {
+            buf.set_style(edge, self.0);
+        }
+    }
+}
+
+/// A `width` x `height` rect in the middle of `area`, shrunk to fit if need be.
+pub fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Too small for anything else, a line per device.
+fn draw_tiny(
+    f: &mut Frame,
+    gpu_list: &[GpuInfo],
+    health: &[Health],
+    samples: &[Option<&Sample>],
+    selected: usize,
+    cfg: &Config,
+    theme: &Theme,
+) -> Vec<(usize, Rect)> {
+    let area = f.size();
+    let items = gpu_list
+        .iter()
+        .zip(health)
+        .zip(samples)
+        .map(
+            |((gpu, health), sample)| match (health.describe(), sample) {
+                (Some(lost), _) => {
+                    ListItem::new(format!("{} {lost}", gpu.index)).style(theme.lost_style())
+                }
+                (None, Some(sample)) => {
+                    ListItem::new(compact::line(gpu.index, sample, cfg, area.width))
+                }
+                (None, None) => ListItem::new(gpu.index.to_string()),
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    f.render_stateful_widget(
+        List::new(items).highlight_style(theme.highlight_style()),
+        area,
+        &mut state,
+    );
+
+    (state.offset()..gpu_list.len())
+        .zip(area.top()..area.bottom())
+        .map(|(index, y)| {
+            (
+                index,
+                Rect {
+                    y,
+                    height: 1,
+                    ..area
+                },
+            )
+        })
+        .collect()
 }
 
-fn draw_fan_speed<'d>(gpu: &GpuInfo<'d>) -> Gauge<'d> {
-    let temps = gpu
-        .inner
-        .num_fans()
-        .expect("This should be impossible as we never call this without having earlier checked.");
-    let avg = (0..temps as usize)
-        .flat_map(|v| gpu.inner.fan_speed(v as u32))
-        .map(|u| u as f64)
-        .sum::<f64>()
-        / temps as f64;
+/// How the devices are connected to each other (if that's been read yet) on top, the selected
+/// device's links with their throughput below.
+#[allow(clippy::too_many_arguments)]
+fn draw_links(
+    f: &mut Frame,
+    area: Rect,
+    gpu_list: &[GpuInfo],
+    selected: usize,
+    topology: Option<&Topology>,
+    links: &HashMap<String, Links>,
+    cfg: &Config,
+    theme: &Theme,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(gpu_list.len() as u16 + 3),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    let block = Block::default().borders(Borders::ALL).title("Topology");
+    match topology.map(Topology::cells) {
+        Some(cells) if !cells.is_empty() => {
+            let widths = (0..cells[0].len())
+                .map(|c| {
+                    let width = cells.iter().map(|row| row[c].chars().count()).max();
+                    Constraint::Length(width.unwrap_or(0) as u16)
+                })
+                .collect::<Vec<_>>();
+            let header = Row::new(cells[0].clone());
+            let rows = cells.into_iter().skip(1).map(Row::new);
+            let rows = rows.enumerate().map(|(i, row)| match i == selected {
+                true => row.style(theme.highlight_style()),
+                false => row,
+            });
+            f.render_widget(
+                Table::new(rows)
+                    .header(header.style(theme.accent_style()))
+                    .widths(&widths)
+                    .column_spacing(2)
+                    .block(block),
+                chunks[0],
+            );
+        }
+        _ => f.render_widget(
+            Paragraph::new("Reading topology...").block(block),
+            chunks[0],
+        ),
+    }
+
+    let gpu = &gpu_list[selected];
+    let title = format!(
+        "NVLinks on [{}] {}",
+        gpu.index,
+        gpu.name().unwrap_or_else(|_| gpu.card_type.clone())
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let device_links = links.get(&gpu.uuid);
+    let readings = device_links.map_or(&[][..], |l| &l.latest.links);
+    if readings.is_empty() {
+        let text = match device_links {
+            Some(_) => "No NVLinks on this device",
+            None => "Reading links...",
+        };
+        f.render_widget(Paragraph::new(text).block(block), chunks[1]);
+        return;
+    }
+
+    let na = || "N/A".to_string();
+    let rows = readings.iter().map(|reading| {
+        // Name the GPU on the other end if it's one of ours.
+        let remote = reading.remote_bus_id.as_deref().map_or_else(na, |remote| {
+            match gpu_list
+                .iter()
+                .find(|other| same_bus_id(&other.pci_bus_id, remote))
+            {
+                Some(other) => format!("{remote} (GPU{})", other.index),
+                None => remote.to_string(),
+            }
+        });
+        let rate = device_links.and_then(|l| l.rate(reading.link));
+        let rate = |pick: fn((f64, f64)) -> f64| {
+            rate.zip(reading.units.as_ref())
+                .map_or_else(na, |(rate, units)| {
+                    nvlink::format_rate(pick(rate), units, cfg.units.memory)
+                })
+        };
+
+        let row = Row::new(vec![
+            reading.link.to_string(),
+            match reading.active {
+                true => "active".into(),
+                false => "inactive".into(),
+            },
+            reading.version.map_or_else(na, |v| v.to_string()),
+            remote,
+            rate(|(rx, _)| rx),
+            rate(|(_, tx)| tx),
+        ]);
+        match reading.active {
+            true => row,
+            false => row.style(theme.lost_style()),
+        }
+    });
 
+    f.render_widget(
+        Table::new(rows)
+            .header(
+                Row::new(vec!["Link", "State", "Version", "Remote", "RX", "TX"])
+                    .style(theme.accent_style()),
+            )
+            .widths(&[
+                Constraint::Length(4),
+                Constraint::Length(8),
+                Constraint::Length(7),
+                Constraint::Min(24),
+                Constraint::Length(14),
+                Constraint::Length(14),
+            ])
+            .block(block),
+        chunks[1],
+    );
+}
+
+/// A row per vGPU running on `gpu`, or why there aren't any.
+fn draw_vgpus(
+    f: &mut Frame,
+    area: Rect,
+    gpu: &GpuInfo,
+    instances: Option<&Vec<VgpuInstance>>,
+    cfg: &Config,
+    theme: &Theme,
+) {
+    let mode = gpu.details.virtualization_mode;
+    let title = format!(
+        "vGPUs on [{}] {} ({})",
+        gpu.index,
+        gpu.name().unwrap_or_else(|_| gpu.card_type.clone()),
+        mode.map_or("virtualization mode N/A".into(), |mode| mode.to_string())
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+
+    let text = match (mode, instances) {
+        (Some(mode), _) if !mode.is_host() => "vGPUs are only listed on a vGPU host",
+        (None, _) => "This device doesn't say whether it's virtualised",
+        (_, None) => "Reading vGPUs...",
+        (_, Some(instances)) if instances.is_empty() => "No vGPUs running",
+        (_, Some(instances)) => {
+            draw_vgpu_table(f, area, instances, block, cfg, theme);
+            return;
+        }
+    };
+    f.render_widget(Paragraph::new(text).block(block), area);
+}
+
+fn draw_vgpu_table(
+    f: &mut Frame,
+    area: Rect,
+    instances: &[VgpuInstance],
+    block: Block,
+    cfg: &Config,
+    theme: &Theme,
+) {
+    let na = || "N/A".to_string();
+    let percent = |value: Option<f64>| value.map_or_else(na, |v| format!("{v:.0}%"));
+    let units = cfg.units.memory;
+    let rows = instances.iter().map(|instance| {
+        let total = instance.vgpu_type.as_ref().and_then(|t| t.framebuffer);
+        let framebuffer = match (instance.fb_used, total) {
+            (Some(used), Some(total)) => format!(
+                "{:.2}/{:.2} {} ({})",
+                units.convert(used),
+                units.convert(total),
+                units.symbol(),
+                percent(instance.fb_percent())
+            ),
+            (Some(used), None) => format!("{:.2} {}", units.convert(used), units.symbol()),
+            (None, _) => na(),
+        };
+
+        Row::new(vec![
+            instance.id.to_string(),
+            instance.vm_id.clone().unwrap_or_else(na),
+            instance
+                .vgpu_type
+                .as_ref()
+                .map_or_else(na, |t| t.name.clone()),
+            percent(instance.utilisation),
+            percent(instance.memory_utilisation),
+            framebuffer,
+            instance.uuid.clone().unwrap_or_else(na),
+        ])
+    });
+
+    f.render_widget(
+        Table::new(rows)
+            .header(
+                Row::new(vec![
+                    "Id",
+                    "VM",
+                    "Type",
+                    "SM",
+                    "Mem",
+                    "Frame buffer",
+                    "UUID",
+                ])
+                .style(theme.accent_style()),
+            )
+            .widths(&[
+                Constraint::Length(6),
+                Constraint::Min(12),
+                Constraint::Length(16),
+                Constraint::Length(5),
+                Constraint::Length(5),
+                Constraint::Length(24),
+                Constraint::Min(36),
+            ])
+            .block(block),
+        area,
+    );
+}
+
+/// `area` less its border.
+fn inner(area: Rect) -> Rect {
+    Block::default().borders(Borders::ALL).inner(area)
+}
+
+/// Grey out the lost device's panels, with what went wrong on top.
+fn draw_lost(f: &mut Frame, area: Rect, lost: &str, keymap: &Keymap, theme: &Theme) {
+    f.render_widget(Block::default().style(theme.lost_style()), area);
+
+    let mut retrying = format!("retrying every {}s", Health::RETRY.as_secs());
+    let rescan = keymap.describe(Action::Rescan);
+    if !rescan.is_empty() {
+        retrying.push_str(&format!(", press {rescan} to rescan"));
+    }
+    let text = vec![
+        Line::from(lost.to_string()),
+        Line::from(""),
+        Line::from(retrying),
+    ];
+    let popup = centered(area, 60, 6);
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(text)
+            .wrap(Wrap { trim: true })
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .title("Device lost")
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(theme.border_style()),
+            ),
+        popup,
+    );
+}
+
+fn draw_help(f: &mut Frame, area: Rect, keymap: &Keymap, theme: &Theme) {
+    let rows = Action::ALL
+        .into_iter()
+        .map(|action| {
+            Row::new(vec![
+                keymap.describe(action),
+                action.description().to_string(),
+            ])
+        })
+        .chain(FixedKey::ALL.map(|key| Row::new(vec![key.keys(), key.description()])))
+        .collect::<Vec<_>>();
+
+    let popup = centered(area, 50, rows.len() as u16 + 2);
+    let table = Table::new(rows)
+        .block(
+            Block::default()
+                .title("Keys")
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(theme.border_style()),
+        )
+        .widths(&[Constraint::Percentage(40), Constraint::Percentage(60)]);
+
+    f.render_widget(Clear, popup);
+    f.render_widget(table, popup);
+}
+
+/// The table, along with the index of the device each row belongs to, MIG instances get a row
+/// each under their parent.
+fn draw_device_table<'d>(
+    gpu_list: &[GpuInfo],
+    health: &[Health],
+    samples: &[Option<&Sample>],
+    instance_samples: &HashMap<String, Sample>,
+    cfg: &Config,
+    theme: &Theme,
+) -> (Table<'d>, Vec<usize>) {
+    let cell =
+        |value: Option<f64>, unit: &str| value.map_or("N/A".into(), |v| format!("{v:.0}{unit}"));
+
+    let mut owners = vec![];
+    let rows = gpu_list
+        .iter()
+        .zip(health)
+        .zip(samples)
+        .enumerate()
+        .flat_map(|(i, ((gpu, health), sample))| {
+            let metric = |metric| sample.and_then(|s| s.metric(metric));
+            let temperature = sample
+                .and_then(|s| s.temperature)
+                .map(|t| cfg.units.temperature.convert(t));
+
+            let mut name = gpu.name().unwrap_or_else(|_| gpu.card_type.clone());
+            if !health.is_healthy() {
+                name.push_str(" (lost)");
+            }
+
+            let row = Row::new(vec![
+                gpu.index.to_string(),
+                name,
+                cell(metric(Metric::Utilisation), "%"),
+                cell(metric(Metric::Memory), "%"),
+                cell(temperature, cfg.units.temperature.symbol()),
+                cell(metric(Metric::Fan), "%"),
+            ]);
+            let row = match health.is_healthy() {
+                true => row,
+                false => row.style(theme.lost_style()),
+            };
+
+            // Temperature and fans belong to the parent, so those are left blank.
+            let instances = gpu.mig.iter().map(move |instance| {
+                let sample = instance_samples.get(&instance.uuid);
+                let metric = |metric| sample.and_then(|s| s.metric(metric));
+                let row = Row::new(vec![
+                    String::new(),
+                    format!("└ {} {}", instance.id(), instance.profile()),
+                    cell(metric(Metric::Utilisation), "%"),
+                    cell(metric(Metric::Memory), "%"),
+                ]);
+                match health.is_healthy() {
+                    true => row,
+                    false => row.style(theme.lost_style()),
+                }
+            });
+
+            owners.push(i);
+            owners.extend(gpu.mig.iter().map(|_| i));
+            [row].into_iter().chain(instances).collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["#", "Name", "Core", "Memory", "Temp", "Fan"])
+                .style(theme.accent_style()),
+        )
+        .widths(&[
+            Constraint::Length(3),
+            Constraint::Min(20),
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(5),
+        ])
+        .highlight_style(theme.highlight_style())
+        .highlight_symbol("> ");
+
+    (table, owners)
+}
+
+/// `info_scroll` is how far down the info panel is scrolled, updated to how far it could go.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_panel(
+    f: &mut Frame,
+    panel: Panel,
+    area: Rect,
+    gpu: &GpuInfo,
+    sample: &Sample,
+    info_scroll: &mut u16,
+    cfg: &Config,
+    theme: &Theme,
+) {
+    match panel {
+        Panel::CoreUtilisation => f.render_widget(draw_core_utilisation(sample, theme), area),
+        Panel::CoreClock => {
+            if let Some(gauge) = draw_core_clock(gpu, sample, cfg, theme) {
+                f.render_widget(gauge, area)
+            }
+        }
+        Panel::Misc => {
+            *info_scroll = draw_info(f, area, gpu, *info_scroll, cfg, theme);
+        }
+        Panel::Memory => f.render_widget(draw_memory_usage(sample, cfg, theme), area),
+        Panel::Temperature => f.render_widget(draw_gpu_die_temp(sample, cfg, theme), area),
+        Panel::Fan => {
+            if let Some(gauge) = draw_fan_speed(sample, cfg, theme) {
+                f.render_widget(gauge, area)
+            }
+        }
+    }
+}
+
+fn draw_fan_speed<'d>(sample: &Sample, cfg: &Config, theme: &Theme) -> Option<Gauge<'d>> {
+    let avg = sample.fan_speed?;
     let percentage = (avg / 100.).clamp(0.0, 1.0);
 
     let label = format!("{:.1}%", avg);
-    let spanned_label = Span::styled(label, Style::new().white().bold().bg(Color::Black));
+    let spanned_label = Span::styled(label, theme.label_style());
 
-    Gauge::default()
-        .block(Block::default().borders(Borders::ALL).title("Fan Speed"))
-        .gauge_style(calculate_severity(percentage).style_for())
-        .label(spanned_label)
-        .set_style(Style::default())
-        .ratio(percentage)
+    Some(
+        Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title("Fan Speed"))
+            .gauge_style(cfg.thresholds.severity_of(percentage).style_for(theme))
+            .label(spanned_label)
+            .set_style(Style::default())
+            .ratio(percentage),
+    )
 }
 
-fn draw_gpu_die_temp<'d>(gpu: &GpuInfo<'d>) -> Gauge<'d> {
-    let gpu_die_temperature = gpu
-        .inner
-        .temperature(TemperatureSensor::Gpu)
-        .map_or(0, |temp| temp);
-
-    let label = format!("{:.2}°C", gpu_die_temperature);
-    let spanned_label = Span::styled(label, Style::new().white().bold().bg(Color::Black));
-    let temp_ratio = (gpu_die_temperature as f64 / 100.).clamp(0.0, 1.0);
+fn draw_gpu_die_temp<'d>(sample: &Sample, cfg: &Config, theme: &Theme) -> Gauge<'d> {
+    let label = sample.temperature.map_or("N/A".into(), |t| {
+        format!(
+            "{:.0}{}",
+            cfg.units.temperature.convert(t),
+            cfg.units.temperature.symbol()
+        )
+    });
+    let spanned_label = Span::styled(label, theme.label_style());
+    let temp_ratio = (f64::from(sample.temperature.unwrap_or(0)) / 100.).clamp(0.0, 1.0);
 
     Gauge::default()
         .block(Block::default().borders(Borders::ALL).title("Temp"))
-        .gauge_style(calculate_severity(temp_ratio).style_for())
+        .gauge_style(cfg.thresholds.severity_of(temp_ratio).style_for(theme))
         .label(spanned_label)
         .set_style(Style::default())
         .ratio(temp_ratio)
 }
 
-fn draw_memory_usage<'d>(gpu: &GpuInfo<'d>) -> Gauge<'d> {
-    let mem_info = gpu.inner.memory_info().map_or(
-        MemoryInfo {
-            free: 0,
-            total: 0, //TODO: This never changes so put in self
-            used: 0,
-        },
-        |mem_info| mem_info,
-    );
-
-    let mem_used = mem_info.used as f64 / 1_073_741_824.0; // as GB
-    let mem_total = mem_info.total as f64 / 1_073_741_824.0;
-    let mem_percentage = (mem_used / mem_total).clamp(0.0, 1.0);
-
-    let label = format!("{:.2}/{:.2}GB", mem_used, mem_total);
-    let spanned_label = Span::styled(label, Style::new().white().bold().bg(Color::Black));
+fn draw_memory_usage<'d>(sample: &Sample, cfg: &Config, theme: &Theme) -> Gauge<'d> {
+    let (label, mem_percentage) = match (sample.memory_used, sample.memory_total) {
+        (Some(used), Some(total)) if total != 0 => {
+            let mem_used = cfg.units.memory.convert(used);
+            let mem_total = cfg.units.memory.convert(total);
+            let label = format!(
+                "{:.2}/{:.2}{}",
+                mem_used,
+                mem_total,
+                cfg.units.memory.symbol()
+            );
+            (label, (mem_used / mem_total).clamp(0.0, 1.0))
+        }
+        _ => ("N/A".into(), 0.0),
+    };
+    let spanned_label = Span::styled(label, theme.label_style());
 
     Gauge::default()
         .block(Block::default().borders(Borders::ALL).title("Memory Usage"))
-        .gauge_style(calculate_severity(mem_percentage).style_for())
+        .gauge_style(cfg.thresholds.severity_of(mem_percentage).style_for(theme))
         .label(spanned_label)
         .ratio(mem_percentage)
 }
 
-fn draw_misc<'d>(gpu: &'d GpuInfo<'d>) -> Paragraph<'d> {
-    let block = Block::default().borders(Borders::ALL).title(Span::styled(
-        "Misc",
-        Style::default()
-            .fg(Color::Magenta)
-            .add_modifier(Modifier::BOLD),
-    ));
+/// The device's details, scrolled down `scroll` lines, returns how far it could actually scroll.
+fn draw_info(
+    f: &mut Frame,
+    area: Rect,
+    gpu: &GpuInfo,
+    scroll: u16,
+    cfg: &Config,
+    theme: &Theme,
+) -> u16 {
+    let rows = gpu.info_rows(cfg);
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let lines = rows
+        .into_iter()
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(format!("{label:label_width$}  "), theme.accent_style()),
+                Span::raw(value),
+            ])
+        })
+        .collect::<Vec<_>>();
+
+    let height = inner(area).height;
+    let max_scroll = (lines.len() as u16).saturating_sub(height);
+    let scroll = scroll.min(max_scroll);
 
-    let spanned_label = Span::styled(&gpu.misc, Style::new().white().bold());
+    let mut title = vec![Span::styled("Device Info", theme.accent_style())];
+    if max_scroll > 0 {
+        let below = max_scroll - scroll;
+        title.push(Span::raw(format!(" ({scroll} above, {below} below)")));
+    }
+
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((scroll, 0)),
+        area,
+    );
 
-    Paragraph::new(spanned_label)
-        .block(block)
-        .wrap(Wrap { trim: true })
+    scroll
 }
 
-fn draw_core_utilisation<'d>(gpu: &GpuInfo<'d>) -> Gauge<'d> {
-    let utilisation_rates = gpu.inner.utilization_rates();
-    let percent = utilisation_rates.map_or(0, |ur| ur.gpu as u16);
+fn draw_core_utilisation<'d>(sample: &Sample, theme: &Theme) -> Gauge<'d> {
+    let percent = sample.utilisation.map_or(0, |u| u.min(100) as u16);
+    let label = match sample.utilisation {
+        Some(_) => format!("{}%", percent),
+        None => "N/A".into(),
+    };
 
-    let spanned_label = Span::styled(
-        format!("{}%", percent),
-        Style::new().white().bold().bg(Color::Black),
-    );
+    let spanned_label = Span::styled(label, theme.label_style());
 
     Gauge::default()
         .block(
@@ -309,27 +1429,82 @@ fn draw_core_utilisation<'d>(gpu: &GpuInfo<'d>) -> Gauge<'d> {
                 .borders(Borders::ALL)
                 .title("Core Utilisation"),
         )
-        .gauge_style(Style {
-            fg: Some(Color::Green),
-            bg: None,
-            underline_color: None,
-            add_modifier: Modifier::BOLD,
-            sub_modifier: Modifier::BOLD,
-        })
+        .gauge_style(theme.border_style().add_modifier(Modifier::BOLD))
         .percent(percent)
         .label(spanned_label)
 }
 
-fn draw_core_clock<'d>(gpu: &GpuInfo<'d>) -> Result<Gauge<'d>, NvTopError> {
-    let current_clock = gpu.inner.clock(Clock::Graphics, ClockId::Current)?;
-    let percentage = (current_clock as f64 / gpu.max_core_clock as f64).clamp(0.0, 1.0);
+fn draw_core_clock<'d>(
+    gpu: &GpuInfo,
+    sample: &Sample,
+    cfg: &Config,
+    theme: &Theme,
+) -> Option<Gauge<'d>> {
+    let current_clock = sample.core_clock?;
+    let (label, percentage) = match gpu.max_core_clock {
+        Some(max) if max != 0 => (
+            format!("{}/{}Mhz", current_clock, max),
+            (current_clock as f64 / max as f64).clamp(0.0, 1.0),
+        ),
+        _ => (format!("{}Mhz", current_clock), 0.0),
+    };
+    let spanned_label = Span::styled(label, theme.label_style());
+
+    Some(
+        Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title("Core Clock"))
+            .gauge_style(cfg.thresholds.severity_of(percentage).style_for(theme))
+            .label(spanned_label)
+            .ratio(percentage),
+    )
+}
 
-    let label = format!("{}/{}Mhz", current_clock, gpu.max_core_clock);
-    let spanned_label = Span::styled(label, Style::new().white().bold().bg(Color::Black));
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(Gauge::default()
-        .block(Block::default().borders(Borders::ALL).title("Core Clock"))
-        .gauge_style(calculate_severity(percentage).style_for())
-        .label(spanned_label)
-        .ratio(percentage))
+    #[test]
+    fn tab_window_keeps_selected_on_screen() {
+        let widths = [10; 16];
+
+        assert_eq!(tab_window(&widths, 0, 45), 0..4);
+        assert_eq!(tab_window(&widths, 3, 45), 0..4);
+        assert_eq!(tab_window(&widths, 9, 45), 6..10);
+        assert_eq!(tab_window(&widths, 15, 45), 12..16);
+        // Even if it doesn't fit at all.
+        assert_eq!(tab_window(&widths, 5, 4), 5..6);
+    }
+
+    #[test]
+    fn typed_indices() {
+        let sixteen = (0..16).collect::<Vec<_>>();
+        let mut entry = IndexEntry::default();
+        assert_eq!(entry.push('1', &sixteen), Some(1));
+        assert_eq!(entry.push('2', &sixteen), Some(12));
+        // 12 can't be followed by anything, so this is a new index.
+        assert_eq!(entry.push('1', &sixteen), Some(1));
+        // 17 is too big, start again from 7.
+        assert_eq!(entry.push('7', &sixteen), Some(7));
+
+        let mut entry = IndexEntry::default();
+        assert_eq!(entry.push('4', &[0, 1, 2, 3]), None);
+        assert_eq!(entry.push('2', &[0, 1, 2, 3]), Some(2));
+    }
+
+    #[test]
+    fn typed_indices_with_filtered_devices() {
+        // As with `--devices 2,3,12`.
+        let filtered = [2, 3, 12];
+        let mut entry = IndexEntry::default();
+        assert_eq!(entry.push('2', &filtered), Some(0));
+        assert_eq!(
+            entry.push('0', &filtered),
+            None,
+            "there's no device 0 any more"
+        );
+        assert_eq!(entry.push('3', &filtered), Some(1));
+        // 1 isn't a device but 12 is.
+        assert_eq!(entry.push('1', &filtered), None);
+        assert_eq!(entry.push('2', &filtered), Some(2));
+    }
 }
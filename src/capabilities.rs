@@ -0,0 +1,254 @@
+use std::{collections::BTreeMap, fmt};
+
+use nvml_wrapper::{
+    enum_wrappers::device::{Clock, ClockId, TemperatureSensor},
+    error::NvmlError,
+    Device,
+};
+
+use crate::config::{Panel, Panels};
+
+/// Something we read from a device that not every device can tell us, consumer cards in
+/// particular return `NotSupported` for plenty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capability {
+    Utilisation,
+    Memory,
+    Temperature,
+    Fan,
+    CoreClock,
+    MaxCoreClock,
+    MaxMemoryClock,
+    CoreCount,
+    NvLink,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 9] = [
+        Capability::Utilisation,
+        Capability::Memory,
+        Capability::Temperature,
+        Capability::Fan,
+        Capability::CoreClock,
+        Capability::MaxCoreClock,
+        Capability::MaxMemoryClock,
+        Capability::CoreCount,
+        Capability::NvLink,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::Utilisation => "utilisation",
+            Capability::Memory => "memory",
+            Capability::Temperature => "temperature",
+            Capability::Fan => "fan speed",
+            Capability::CoreClock => "core clock",
+            Capability::MaxCoreClock => "max core clock",
+            Capability::MaxMemoryClock => "max memory clock",
+            Capability::CoreCount => "core count",
+            Capability::NvLink => "nvlink",
+        }
+    }
+
+    /// What `panel` needs to be worth drawing, if anything.
+    pub fn needed_by(panel: Panel) -> Option<Self> {
+        match panel {
+            Panel::CoreUtilisation => Some(Capability::Utilisation),
+            Panel::CoreClock => Some(Capability::CoreClock),
+            Panel::Memory => Some(Capability::Memory),
+            Panel::Temperature => Some(Capability::Temperature),
+            Panel::Fan => Some(Capability::Fan),
+            Panel::Misc => None,
+        }
+    }
+}
+
+/// How a [`Capability`] fared when we tried it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Support {
+    Yes,
+    NotSupported,
+    NoPermission,
+    Failed(String),
+}
+
+impl<T> From<Result<T, NvmlError>> for Support {
+    fn from(result: Result<T, NvmlError>) -> Self {
+        match result {
+            Ok(_) => Support::Yes,
+            Err(NvmlError::NotSupported) => Support::NotSupported,
+            Err(NvmlError::NoPermission) => Support::NoPermission,
+            Err(e) => Support::Failed(e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Support {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Support::Yes => write!(f, "yes"),
+            Support::NotSupported => write!(f, "not supported"),
+            Support::NoPermission => write!(f, "no permission"),
+            Support::Failed(e) => write!(f, "error: {e}"),
+        }
+    }
+}
+
+/// What a device told us it can do, probed once when it's set up.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Capabilities(BTreeMap<Capability, Support>);
+
+impl Capabilities {
+    pub fn probe(device: &Device) -> Self {
+        let fan = match device.num_fans() {
+            Ok(0) => Support::NotSupported,
+            Ok(_) => device.fan_speed(0).into(),
+            Err(e) => Err::<(), _>(e).into(),
+        };
+
+        Self(BTreeMap::from([
+            (Capability::Utilisation, device.utilization_rates().into()),
+            (Capability::Memory, device.memory_info().into()),
+            (
+                Capability::Temperature,
+                device.temperature(TemperatureSensor::Gpu).into(),
+            ),
+            (Capability::Fan, fan),
+            (
+                Capability::CoreClock,
+                device.clock(Clock::Graphics, ClockId::Current).into(),
+            ),
+            (
+                Capability::MaxCoreClock,
+                device.max_clock_info(Clock::Graphics).into(),
+            ),
+            (
+                Capability::MaxMemoryClock,
+                device.max_clock_info(Clock::Memory).into(),
+            ),
+            (Capability::CoreCount, device.num_cores().into()),
+            (
+                Capability::NvLink,
+                device.link_wrapper_for(0).is_active().into(),
+            ),
+        ]))
+    }
+
+    /// Anything that wasn't probed is assumed to work.
+    pub fn supports(&self, capability: Capability) -> bool {
+        matches!(self.0.get(&capability), None | Some(Support::Yes))
+    }
+
+    pub fn get(&self, capability: Capability) -> Option<&Support> {
+        self.0.get(&capability)
+    }
+
+    /// `panels` less any this device can't fill.
+    pub fn visible_panels(&self, panels: &Panels) -> Panels {
+        let mut visible = *panels;
+        for panel in Panel::ALL {
+            if Capability::needed_by(panel).is_some_and(|c| !self.supports(c)) {
+                *visible.visible_mut(panel) = false;
+            }
+        }
+        visible
+    }
+}
+
+impl FromIterator<(Capability, Support)> for Capabilities {
+    fn from_iter<I: IntoIterator<Item = (Capability, Support)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// `nvtop --capabilities`: a row per [`Capability`] and a column per device, i.e:
+/// ```text
+///                   [0] NVIDIA GeForce RTX 3090  [1] Tesla T4
+/// utilisation       yes                          yes
+/// fan speed         yes                          not supported
+/// ```
+pub fn matrix(devices: &[(String, &Capabilities)]) -> String {
+    let first = Capability::ALL
+        .iter()
+        .map(|c| c.name().len())
+        .max()
+        .unwrap_or(0);
+    let cell = |capabilities: &Capabilities, capability| {
+        capabilities
+            .get(capability)
+            .map_or("-".to_string(), Support::to_string)
+    };
+    let widths = devices
+        .iter()
+        .map(|(name, capabilities)| {
+            Capability::ALL
+                .iter()
+                .map(|&c| cell(capabilities, c).chars().count())
+                .chain([name.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let row = |label: &str, cells: Vec<String>| {
+        let mut line = format!("{label:first$}");
+        for (cell, width) in cells.iter().zip(&widths) {
+            line.push_str(&format!("  {cell:width$}"));
+        }
+        line.trim_end().to_string()
+    };
+
+    let mut lines = vec![row(
+        "",
+        devices.iter().map(|(name, _)| name.clone()).collect(),
+    )];
+    for capability in Capability::ALL {
+        lines.push(row(
+            capability.name(),
+            devices
+                .iter()
+                .map(|(_, capabilities)| cell(capabilities, capability))
+                .collect(),
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_panels_are_hidden() {
+        let capabilities = Capabilities::from_iter([
+            (Capability::Fan, Support::NotSupported),
+            (Capability::Utilisation, Support::Yes),
+            (Capability::Temperature, Support::NoPermission),
+        ]);
+
+        assert!(capabilities.supports(Capability::Utilisation));
+        // Not probed, so given the benefit of the doubt.
+        assert!(capabilities.supports(Capability::Memory));
+        assert!(!capabilities.supports(Capability::Fan));
+
+        let visible = capabilities.visible_panels(&Panels::default());
+        assert!(!visible.fan && !visible.temperature);
+        assert!(visible.core_utilisation && visible.memory && visible.misc);
+    }
+
+    #[test]
+    fn capability_matrix() {
+        let consumer = Capabilities::from_iter([
+            (Capability::Utilisation, Support::Yes),
+            (Capability::Fan, Support::NotSupported),
+        ]);
+        let matrix = matrix(&[("[0] Tesla T4".into(), &consumer)]);
+        let lines = matrix.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "                  [0] Tesla T4");
+        assert_eq!(lines[1], "utilisation       yes");
+        assert_eq!(lines[4], "fan speed         not supported");
+        assert_eq!(lines[5], "core clock        -");
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossterm::{cursor, queue, style::Print, terminal};
+use nvml_wrapper::Nvml;
+
+use crate::{
+    alerts::Alerter,
+    config::Config,
+    errors::NvTopError,
+    gpu::try_init_gpus,
+    health::{sample_devices, Health},
+    mig::sample_instances,
+    sample::Sample,
+    terminal::Signals,
+    termite::LoggingHandle,
+};
+
+/// One device (or MIG instance) on one line, cut down to fit in `width` columns, i.e:
+/// `0 ▕███████░░░▏ 71%  64°C  21.3/24.0GiB  fan 45%`
+pub fn line(label: impl fmt::Display, sample: &Sample, cfg: &Config, width: u16) -> String {
+    let width = usize::from(width);
+    let or_na = |v: Option<String>| v.unwrap_or_else(|| "N/A".into());
+
+    let utilisation = or_na(sample.utilisation.map(|u| format!("{u:>3}%")));
+    let temperature = or_na(sample.temperature.map(|t| {
+        let units = cfg.units.temperature;
+        format!("{:.0}{}", units.convert(t), units.symbol())
+    }));
+    let memory = or_na(
+        sample
+            .memory_used
+            .zip(sample.memory_total)
+            .map(|(used, total)| {
+                let units = cfg.units.memory;
+                format!(
+                    "{:.1}/{:.1}{}",
+                    units.convert(used),
+                    units.convert(total),
+                    units.symbol()
+                )
+            }),
+    );
+
+    let mut rest = format!("{utilisation}  {temperature}  {memory}");
+    if let Some(fan) = sample.fan_speed {
+        rest.push_str(&format!("  fan {fan:.0}%"));
+    }
+    let mut line = format!("{label} {rest}");
+
+    // Add a utilisation bar if there's room for one.
+    const BAR: usize = 10;
+    if let Some(u) = sample.utilisation {
+        if line.chars().count() + BAR + 2 <= width {
+            let filled = (u.min(100) as usize * BAR + 50) / 100;
+            let bar = format!("▕{}{}▏", "█".repeat(filled), "░".repeat(BAR - filled));
+            line = format!("{label} {bar}{rest}");
+        }
+    }
+
+    line.chars().take(width).collect()
+}
+
+/// `nvtop --compact`: a line per device, updated in place every `delay` until interrupted.
+pub fn run(
+    nvml: Nvml,
+    cfg: &Config,
+    lh: &LoggingHandle,
+    alerter: &mut Alerter,
+) -> Result<(), NvTopError> {
+    let signals = Signals::register()?;
+    let mut gpu_list = try_init_gpus(&nvml, &cfg.device_filters(), lh)?;
+    let mut health = vec![Health::default(); gpu_list.len()];
+    let delay = Duration::from_millis(cfg.delay);
+    let mut stdout = io::stdout();
+    let mut printed = 0;
+
+    while !signals.quit_requested() {
+        let started = Instant::now();
+        // Anything wider than the terminal would wrap and throw off the redraw.
+        let width = terminal::size().map_or(u16::MAX, |(w, _)| w.saturating_sub(1));
+
+        if printed > 0 {
+            queue!(stdout, cursor::MoveToPreviousLine(printed))?;
+        }
+        let samples = sample_devices(&nvml, &mut gpu_list, &mut health, lh);
+        let instance_samples = sample_instances(&gpu_list, &health);
+        let mut lines = vec![];
+        for ((gpu, health), sample) in gpu_list.iter().zip(&health).zip(samples) {
+            let text = match health.describe() {
+                Some(lost) => format!("{} {lost}", gpu.index)
+                    .chars()
+                    .take(usize::from(width))
+                    .collect(),
+                None => {
+                    alerter.check(gpu.index, &gpu.uuid, &sample);
+                    line(gpu.index, &sample, cfg, width)
+                }
+            };
+            lines.push(text);
+
+            // MIG instances go under their parent, N/A while it's lost.
+            for instance in &gpu.mig {
+                let sample = instance_samples
+                    .get(&instance.uuid)
+                    .cloned()
+                    .unwrap_or_else(Sample::missing);
+                let label = format!("  {}", instance.label(gpu.index));
+                lines.push(line(label, &sample, cfg, width));
+            }
+        }
+        for text in &lines {
+            queue!(
+                stdout,
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                Print(text),
+                Print("\n")
+            )?;
+        }
+        // Anything left over from a longer previous frame, i.e a device that's gone.
+        queue!(stdout, terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        printed = lines.len() as u16;
+        stdout.flush()?;
+
+        while started.elapsed() < delay && !signals.quit_requested() {
+            thread::sleep(Duration::from_millis(20).min(delay));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_fit_the_width() {
+        let sample = Sample {
+            taken: Instant::now(),
+            utilisation: Some(71),
+            memory_used: Some(21_300_000_000),
+            memory_total: Some(24_000_000_000),
+            temperature: Some(64),
+            fan_speed: Some(45.0),
+            ..Sample::missing()
+        };
+        let mut cfg = Config::default();
+        cfg.units.memory = crate::config::MemoryUnit::GB;
+
+        assert_eq!(
+            line(0, &sample, &cfg, 80),
+            "0 ▕███████░░░▏ 71%  64°C  21.3/24.0GB  fan 45%"
+        );
+        assert_eq!(
+            line(0, &sample, &cfg, 40),
+            "0  71%  64°C  21.3/24.0GB  fan 45%"
+        );
+        assert_eq!(line(12, &sample, &cfg, 12), "12  71%  64°");
+
+        let empty = Sample {
+            utilisation: None,
+            memory_used: None,
+            temperature: None,
+            fan_speed: None,
+            ..sample
+        };
+        assert_eq!(line(1, &empty, &cfg, 80), "1 N/A  N/A  N/A");
+    }
+}
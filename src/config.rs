@@ -0,0 +1,526 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    devices::DeviceFilter,
+    errors::NvTopError,
+    keymap::Keymap,
+    layout::{Content, LayoutNode, Layouts},
+    stylers::{SeverityColors, SeverityThresholds},
+    theme::ThemeName,
+};
+
+/// Everything that can be set from `config.toml`, missing fields fall back to their defaults, i.e:
+/// ```toml
+/// delay = 250
+/// theme = "solarized"
+///
+/// [thresholds]
+/// medium = 50
+///
+/// [keys]
+/// quit = ["x", "ctrl-c"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Amount of time to wait between refreshes, in millis, at least [`Config::MIN_DELAY`].
+    #[serde(deserialize_with = "delay")]
+    pub delay: u64,
+    pub theme: ThemeName,
+    pub thresholds: SeverityThresholds,
+    /// Overrides the theme's severity colours when set.
+    pub colors: Option<SeverityColors>,
+    pub layout: LayoutConfig,
+    /// Extra dashboards by name, see [`LayoutNode`].
+    pub layouts: BTreeMap<String, LayoutNode>,
+    pub keys: Keymap,
+    pub units: Units,
+    pub panels: Panels,
+    /// Click to select devices and panels, scroll lists with the wheel.
+    pub mouse: bool,
+    /// Number of samples kept per device to step back through while frozen.
+    pub history: usize,
+    /// Only show these devices, i.e `"0,2"`, `"GPU-5b3c"`, `"0000:0a:00.0"` or `"*A100*"`.
+    pub devices: DeviceFilter,
+    /// Only show the devices in `CUDA_VISIBLE_DEVICES` and `NVIDIA_VISIBLE_DEVICES` when they're set.
+    pub visible_devices: bool,
+    /// Seconds between checks for devices being added or removed, 0 to only rescan with `p`.
+    pub rescan: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            delay: 100,
+            theme: ThemeName::default(),
+            thresholds: SeverityThresholds::default(),
+            colors: None,
+            layout: LayoutConfig::default(),
+            layouts: BTreeMap::new(),
+            keys: Keymap::default(),
+            units: Units::default(),
+            panels: Panels::default(),
+            mouse: true,
+            history: 600,
+            devices: DeviceFilter::default(),
+            visible_devices: false,
+            rescan: 5,
+        }
+    }
+}
+
+/// Percentages used to split up the screen in the `default` layout, and which layout to start with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// Left and right columns.
+    #[serde(deserialize_with = "percentages")]
+    pub main: [u16; 2],
+    /// Core utilisation, core clock and misc, top to bottom.
+    #[serde(deserialize_with = "percentages")]
+    pub left: [u16; 3],
+    /// Memory, temperature and fan speed, top to bottom.
+    #[serde(deserialize_with = "percentages")]
+    pub right: [u16; 3],
+    /// Name of the layout to start with.
+    pub start: String,
+    /// Width and height below which the `compact` layout is used.
+    pub compact_below: [u16; 2],
+    /// Width and height below which there's only room for a line per device.
+    pub table_below: [u16; 2],
+}
+
+impl LayoutConfig {
+    /// The classic nvtop dashboard, two columns of three panels.
+    pub fn to_node(&self) -> LayoutNode {
+        let column = |size, panels: [Panel; 3], sizes: [u16; 3]| LayoutNode {
+            size: Some(size),
+            content: Content::Rows(
+                panels
+                    .into_iter()
+                    .zip(sizes)
+                    .map(|(panel, size)| LayoutNode::widget(panel, Some(size)))
+                    .collect(),
+            ),
+        };
+
+        LayoutNode {
+            size: None,
+            content: Content::Columns(vec![
+                column(
+                    self.main[0],
+                    [Panel::CoreUtilisation, Panel::CoreClock, Panel::Misc],
+                    self.left,
+                ),
+                column(
+                    self.main[1],
+                    [Panel::Memory, Panel::Temperature, Panel::Fan],
+                    self.right,
+                ),
+            ]),
+        }
+    }
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            main: [70, 30],
+            left: [60, 20, 20],
+            right: [33, 33, 33],
+            start: Layouts::DEFAULT.into(),
+            compact_below: [60, 16],
+            table_below: [50, 12],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub memory: MemoryUnit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub fn convert(&self, celsius: u32) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => f64::from(celsius),
+            TemperatureUnit::Fahrenheit => f64::from(celsius) * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            TemperatureUnit::Celsius => TemperatureUnit::Fahrenheit,
+            TemperatureUnit::Fahrenheit => TemperatureUnit::Celsius,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MemoryUnit {
+    /// 10^9 bytes
+    GB,
+    /// 2^30 bytes
+    #[default]
+    GiB,
+    /// 2^20 bytes
+    MiB,
+}
+
+impl MemoryUnit {
+    pub fn convert(&self, bytes: u64) -> f64 {
+        match self {
+            MemoryUnit::GB => bytes as f64 / 1_000_000_000.0,
+            MemoryUnit::GiB => bytes as f64 / 1_073_741_824.0,
+            MemoryUnit::MiB => bytes as f64 / 1_048_576.0,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            MemoryUnit::GB => "GB",
+            MemoryUnit::GiB => "GiB",
+            MemoryUnit::MiB => "MiB",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            MemoryUnit::GB => MemoryUnit::GiB,
+            MemoryUnit::GiB => MemoryUnit::MiB,
+            MemoryUnit::MiB => MemoryUnit::GB,
+        }
+    }
+}
+
+/// The widgets drawn for the selected device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Panel {
+    CoreUtilisation,
+    CoreClock,
+    Misc,
+    Memory,
+    Temperature,
+    Fan,
+}
+
+impl Panel {
+    pub const ALL: [Panel; 6] = [
+        Panel::CoreUtilisation,
+        Panel::CoreClock,
+        Panel::Misc,
+        Panel::Memory,
+        Panel::Temperature,
+        Panel::Fan,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Panel::CoreUtilisation => "Core Utilisation",
+            Panel::CoreClock => "Core Clock",
+            Panel::Misc => "Device Info",
+            Panel::Memory => "Memory Usage",
+            Panel::Temperature => "Temp",
+            Panel::Fan => "Fan Speed",
+        }
+    }
+}
+
+/// Which [`Panel`]s are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Panels {
+    pub core_utilisation: bool,
+    pub core_clock: bool,
+    pub misc: bool,
+    pub memory: bool,
+    pub temperature: bool,
+    pub fan: bool,
+}
+
+impl Default for Panels {
+    fn default() -> Self {
+        Self {
+            core_utilisation: true,
+            core_clock: true,
+            misc: true,
+            memory: true,
+            temperature: true,
+            fan: true,
+        }
+    }
+}
+
+impl Panels {
+    pub fn visible_mut(&mut self, panel: Panel) -> &mut bool {
+        match panel {
+            Panel::CoreUtilisation => &mut self.core_utilisation,
+            Panel::CoreClock => &mut self.core_clock,
+            Panel::Misc => &mut self.misc,
+            Panel::Memory => &mut self.memory,
+            Panel::Temperature => &mut self.temperature,
+            Panel::Fan => &mut self.fan,
+        }
+    }
+
+    pub fn is_visible(&self, panel: Panel) -> bool {
+        match panel {
+            Panel::CoreUtilisation => self.core_utilisation,
+            Panel::CoreClock => self.core_clock,
+            Panel::Misc => self.misc,
+            Panel::Memory => self.memory,
+            Panel::Temperature => self.temperature,
+            Panel::Fan => self.fan,
+        }
+    }
+}
+
+impl Config {
+    /// Any less and the UI would spin waiting on the next sample.
+    pub const MIN_DELAY: u64 = 10;
+
+    /// Where we look for a config when `--config` isn't given:
+    /// `$XDG_CONFIG_HOME/nvtop/config.toml`, falling back to `~/.config/nvtop/config.toml` (`%APPDATA%\nvtop\config.toml` on Windows).
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                #[cfg(target_os = "windows")]
+                let dir = std::env::var_os("APPDATA").map(PathBuf::from);
+                #[cfg(not(target_os = "windows"))]
+                let dir = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"));
+                dir
+            })?;
+
+        Some(config_home.join("nvtop").join("config.toml"))
+    }
+
+    /// Load the config from `path`, or from [`Config::default_path`] if it exists, otherwise the defaults.
+    pub fn load(path: Option<&Path>) -> Result<Self, NvTopError> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| NvTopError::Config(format!("Unable to read {}: {e}", path.display())))?;
+
+        Self::from_toml(&contents)
+            .map_err(|e| NvTopError::Config(format!("Invalid config {}: {e}", path.display())))
+    }
+
+    /// Write the config out to `path`, creating the parent directory if need be.
+    pub fn save(&self, path: &Path) -> Result<(), NvTopError> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|e| NvTopError::Config(format!("Unable to serialise config: {e}")))?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)?;
+
+        Ok(())
+    }
+
+    /// What decides which devices are shown, each with where it came from, a device has to match
+    /// all of them.
+    pub fn device_filters(&self) -> Vec<(&'static str, DeviceFilter)> {
+        let mut filters = vec![];
+        if self.devices != DeviceFilter::All {
+            filters.push(("devices", self.devices.clone()));
+        }
+        if self.visible_devices {
+            filters.extend(DeviceFilter::from_env());
+        }
+        filters
+    }
+
+    /// Parse a config, the error message points at the offending line.
+    pub fn from_toml(contents: &str) -> Result<Self, NvTopError> {
+        let cfg: Self = toml::from_str(contents).map_err(|e| NvTopError::Config(e.to_string()))?;
+
+        // Needs the `[layouts.<name>]` tables too, so it can't be checked as `start` is read.
+        let names = cfg.layout_names();
+        if !names.contains(&cfg.layout.start.as_str()) {
+            return Err(NvTopError::Config(format!(
+                "unknown layout \"{}\" to start with, expected one of: {}",
+                cfg.layout.start,
+                names.join(", ")
+            )));
+        }
+
+        Ok(cfg)
+    }
+
+    /// `default`, `compact` and any from `[layouts.<name>]`.
+    fn layout_names(&self) -> Vec<&str> {
+        let mut names = vec![Layouts::DEFAULT, Layouts::COMPACT];
+        for name in self.layouts.keys() {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        names
+    }
+}
+
+fn delay<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let delay = u64::deserialize(deserializer)?;
+    if delay < Config::MIN_DELAY {
+        return Err(D::Error::custom(format!(
+            "delay of {delay}ms is too short, it needs to be at least {}ms",
+            Config::MIN_DELAY
+        )));
+    }
+
+    Ok(delay)
+}
+
+fn percentages<'de, D, const N: usize>(deserializer: D) -> Result<[u16; N], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let values = Vec::<u16>::deserialize(deserializer)?;
+    // Wide enough that no number of u16s from the file can overflow it.
+    let total = values.iter().map(|&v| u32::from(v)).sum::<u32>();
+    if total > 100 {
+        return Err(D::Error::custom(format!(
+            "percentages add up to {total}, which is more than 100"
+        )));
+    }
+
+    let len = values.len();
+    values
+        .try_into()
+        .map_err(|_| D::Error::custom(format!("expected {N} percentages, got {len}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Action;
+
+    #[test]
+    fn empty_config_is_default() {
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn partial_config_keeps_other_defaults() {
+        let config = Config::from_toml(
+            r#"
+            delay = 500
+            theme = "colour-blind"
+
+            [thresholds]
+            medium = 50
+
+            [colors]
+            critical = [255, 0, 0]
+
+            [layout]
+            main = [50, 50]
+
+            [keys]
+            quit = "x"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.delay, 500);
+        assert_eq!(config.theme, ThemeName::ColourBlind);
+        assert_eq!(config.thresholds.medium, 50.0);
+        assert_eq!(config.thresholds.high, 70.0);
+        let colors = config.colors.unwrap();
+        assert_eq!(colors.critical, (255, 0, 0));
+        assert_eq!(colors.low, SeverityColors::default().low);
+        assert_eq!(config.layout.main, [50, 50]);
+        assert_eq!(config.layout.left, [60, 20, 20]);
+        assert_eq!(config.keys.describe(Action::Quit), "x");
+        assert_eq!(config.keys.describe(Action::Rescan), "p");
+    }
+
+    #[test]
+    fn saved_config_round_trips() {
+        let mut config = Config {
+            delay: 1000,
+            ..Default::default()
+        };
+        config.thresholds.critical = 95.0;
+        config.units.temperature = TemperatureUnit::Fahrenheit;
+        config.units.memory = MemoryUnit::MiB;
+        config.panels.fan = false;
+
+        let path = std::env::temp_dir()
+            .join(format!("nvtop-config-{}", std::process::id()))
+            .join("config.toml");
+        config.save(&path).unwrap();
+        let loaded = Config::load(Some(&path));
+        _ = fs::remove_dir_all(path.parent().unwrap());
+
+        assert_eq!(loaded.unwrap(), config);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_line() {
+        let err = Config::from_toml("delay = 100\n\n[layout]\nleft = [60, 60, 20]\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 4"), "{err}");
+        assert!(err.contains("more than 100"), "{err}");
+
+        let err = Config::from_toml("[thresholds]\nmedium = 90\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 1"), "{err}");
+        assert!(err.contains("medium <= high"), "{err}");
+
+        assert!(Config::from_toml("colour = 1").is_err());
+
+        let err = Config::from_toml("delay = 0\n").unwrap_err().to_string();
+        assert!(err.contains("at least 10ms"), "{err}");
+    }
+
+    #[test]
+    fn percentages_that_would_overflow() {
+        let err = Config::from_toml("[layout]\nmain = [65535, 2]\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("add up to 65537"), "{err}");
+    }
+}
@@ -0,0 +1,210 @@
+use nvml_wrapper::Device;
+
+use crate::{
+    config::Config,
+    raw::{self, MigMode},
+    vgpu::{self, VgpuType, VirtualizationMode},
+};
+
+/// Everything about a device that stays put while it's plugged in, read once when it's set up
+/// for the info panel. `None` for anything the device won't tell us.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceDetails {
+    pub name: Option<String>,
+    pub uuid: Option<String>,
+    pub serial: Option<String>,
+    pub board_part_number: Option<String>,
+    pub vbios_version: Option<String>,
+    pub architecture: Option<String>,
+    /// CUDA compute capability, major and minor.
+    pub compute_capability: Option<(i32, i32)>,
+    pub pci_bus_id: Option<String>,
+    /// Device id in the top 16 bits, vendor id in the bottom.
+    pub pci_device_id: Option<u32>,
+    pub pci_subsystem_id: Option<u32>,
+    /// Bits
+    pub memory_bus_width: Option<u32>,
+    pub num_cores: Option<u32>,
+    /// Bytes
+    pub memory_total: Option<u64>,
+    /// Linux only.
+    pub persistence_mode: Option<bool>,
+    pub compute_mode: Option<String>,
+    /// Whether a display is initialised on the device.
+    pub display_active: Option<bool>,
+    /// Whether a display is connected to the device.
+    pub display_mode: Option<bool>,
+    pub mig_mode: Option<MigMode>,
+    /// Windows only, WDDM or TCC, along with what it'll be after a reboot if that's different.
+    pub driver_model: Option<String>,
+    pub virtualization_mode: Option<VirtualizationMode>,
+    /// The vGPUs this device can host, only read on a vGPU host.
+    pub vgpu_types: Vec<VgpuType>,
+}
+
+impl DeviceDetails {
+    pub fn read(device: &Device) -> Self {
+        let pci = device.pci_info().ok();
+
+        #[cfg(target_os = "linux")]
+        let persistence_mode = device.is_in_persistent_mode().ok();
+        #[cfg(not(target_os = "linux"))]
+        let persistence_mode = None;
+
+        #[cfg(target_os = "windows")]
+        let driver_model =
+            device
+                .driver_model()
+                .ok()
+                .map(|model| match model.current == model.pending {
+                    true => format!("{:?}", model.current),
+                    false => format!("{:?} ({:?} after reboot)", model.current, model.pending),
+                });
+        #[cfg(not(target_os = "windows"))]
+        let driver_model = None;
+
+        let virtualization_mode = raw::virtualization_mode(device).ok();
+        let vgpu_types = match virtualization_mode {
+            Some(mode) if mode.is_host() => VgpuType::supported(device).unwrap_or_default(),
+            _ => vec![],
+        };
+
+        Self {
+            name: device.name().ok(),
+            uuid: device.uuid().ok(),
+            serial: device.serial().ok(),
+            board_part_number: device.board_part_number().ok(),
+            vbios_version: device.vbios_version().ok(),
+            architecture: device.architecture().ok().map(|a| a.to_string()),
+            compute_capability: device
+                .cuda_compute_capability()
+                .ok()
+                .map(|cc| (cc.major, cc.minor)),
+            pci_device_id: pci.as_ref().map(|pci| pci.pci_device_id),
+            pci_subsystem_id: pci.as_ref().and_then(|pci| pci.pci_sub_system_id),
+            pci_bus_id: pci.map(|pci| pci.bus_id),
+            memory_bus_width: device.memory_bus_width().ok(),
+            num_cores: device.num_cores().ok(),
+            memory_total: device.memory_info().ok().map(|mi| mi.total),
+            persistence_mode,
+            compute_mode: device.compute_mode().ok().map(|mode| format!("{mode:?}")),
+            display_active: device.is_display_active().ok(),
+            display_mode: device.is_display_connected().ok(),
+            mig_mode: raw::mig_mode(device).ok(),
+            driver_model,
+            virtualization_mode,
+            vgpu_types,
+        }
+    }
+
+    /// A label and value for each line of the info panel, `N/A` where the device didn't say.
+    pub fn rows(&self, cfg: &Config) -> Vec<(&'static str, String)> {
+        let text = |value: &Option<String>| value.clone().unwrap_or_else(|| "N/A".into());
+        let flag = |value: Option<bool>, on: &str, off: &str| {
+            value.map_or("N/A".into(), |v| if v { on } else { off }.to_string())
+        };
+        let id = |value: Option<u32>| value.map_or("N/A".into(), |id| format!("{id:#010x}"));
+
+        vec![
+            ("Name", text(&self.name)),
+            ("UUID", text(&self.uuid)),
+            ("Serial", text(&self.serial)),
+            ("Board part number", text(&self.board_part_number)),
+            ("VBIOS", text(&self.vbios_version)),
+            ("Architecture", text(&self.architecture)),
+            (
+                "Compute capability",
+                self.compute_capability
+                    .map_or("N/A".into(), |(major, minor)| format!("{major}.{minor}")),
+            ),
+            ("PCI bus id", text(&self.pci_bus_id)),
+            ("PCI device id", id(self.pci_device_id)),
+            ("PCI subsystem id", id(self.pci_subsystem_id)),
+            (
+                "Memory bus width",
+                self.memory_bus_width
+                    .map_or("N/A".into(), |bits| format!("{bits} bit")),
+            ),
+            (
+                "CUDA cores",
+                self.num_cores.map_or("N/A".into(), |n| n.to_string()),
+            ),
+            (
+                "Total memory",
+                self.memory_total.map_or("N/A".into(), |bytes| {
+                    let units = cfg.units.memory;
+                    format!("{:.2} {}", units.convert(bytes), units.symbol())
+                }),
+            ),
+            (
+                "Persistence mode",
+                flag(self.persistence_mode, "enabled", "disabled"),
+            ),
+            ("Compute mode", text(&self.compute_mode)),
+            ("Display active", flag(self.display_active, "yes", "no")),
+            (
+                "Display mode",
+                flag(self.display_mode, "connected", "disconnected"),
+            ),
+            (
+                "MIG mode",
+                self.mig_mode.map_or("N/A".into(), |mig| {
+                    let state = |on: bool| if on { "enabled" } else { "disabled" };
+                    match mig.current == mig.pending {
+                        true => state(mig.current).into(),
+                        false => format!(
+                            "{} ({} after reset)",
+                            state(mig.current),
+                            state(mig.pending)
+                        ),
+                    }
+                }),
+            ),
+            ("Driver model", text(&self.driver_model)),
+            (
+                "Virtualization mode",
+                self.virtualization_mode
+                    .map_or("N/A".into(), |mode| mode.to_string()),
+            ),
+            (
+                "vGPU types",
+                match self.virtualization_mode {
+                    Some(mode) if mode.is_host() && self.vgpu_types.is_empty() => "none".into(),
+                    Some(mode) if mode.is_host() => vgpu::type_names(&self.vgpu_types),
+                    _ => "N/A".into(),
+                },
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_fill_in_missing_values() {
+        let details = DeviceDetails {
+            name: Some("NVIDIA GeForce RTX 3090".into()),
+            compute_capability: Some((8, 6)),
+            pci_device_id: Some(0x2204_10de),
+            memory_total: Some(24 * 1_073_741_824),
+            mig_mode: Some(MigMode {
+                current: false,
+                pending: true,
+            }),
+            ..Default::default()
+        };
+        let rows = details.rows(&Config::default());
+        let row = |label| rows.iter().find(|(l, _)| *l == label).unwrap().1.as_str();
+
+        assert_eq!(row("Name"), "NVIDIA GeForce RTX 3090");
+        assert_eq!(row("Serial"), "N/A");
+        assert_eq!(row("Compute capability"), "8.6");
+        assert_eq!(row("PCI device id"), "0x220410de");
+        assert_eq!(row("Total memory"), "24.00 GiB");
+        assert_eq!(row("MIG mode"), "disabled (enabled after reset)");
+        assert_eq!(row("Display active"), "N/A");
+        assert_eq!(row("vGPU types"), "N/A");
+    }
+}
@@ -0,0 +1,304 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// What a device can be picked by, read once when it's enumerated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Identity<'a> {
+    /// NVML's index, which follows the PCI bus order and can change when devices come and go.
+    pub index: u32,
+    /// i.e `GPU-5b3c6d2e-...`, the only thing that's stable across reboots and rescans.
+    pub uuid: &'a str,
+    /// i.e `00000000:0A:00.0`
+    pub pci_bus_id: &'a str,
+    pub name: &'a str,
+}
+
+/// One entry of a device list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    Index(u32),
+    /// A UUID or a unique start of one, like CUDA accepts, compared case insensitively.
+    Uuid(String),
+    /// Domain, bus, device and function.
+    PciBusId(u32, u8, u8, u8),
+    /// A case insensitive glob on the device name, `*` and `?` are wildcards.
+    Name(String),
+}
+
+impl Selector {
+    pub fn matches(&self, id: &Identity) -> bool {
+        match self {
+            Selector::Index(index) => *index == id.index,
+            Selector::Uuid(uuid) => id
+                .uuid
+                .get(..uuid.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(uuid)),
+            Selector::PciBusId(..) => parse_bus_id(id.pci_bus_id).as_ref() == Some(self),
+            Selector::Name(pattern) => glob(
+                &pattern.to_lowercase().chars().collect::<Vec<_>>(),
+                &id.name.to_lowercase().chars().collect::<Vec<_>>(),
+            ),
+        }
+    }
+
+    /// The only things `CUDA_VISIBLE_DEVICES` understands.
+    fn parse_visible(s: &str) -> Option<Self> {
+        match s.parse::<u32>() {
+            Ok(index) => Some(Selector::Index(index)),
+            Err(_) if is_uuid(s) => Some(Selector::Uuid(s.into())),
+            Err(_) => None,
+        }
+    }
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    /// An index, a UUID (starting `GPU-` or `MIG-`), a PCI bus id (anything with a `:`) or a name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("empty device".into());
+        }
+
+        if let Some(selector) = Selector::parse_visible(s) {
+            return Ok(selector);
+        }
+        if s.contains(':') {
+            return parse_bus_id(s).ok_or_else(|| {
+                format!("'{s}' isn't a PCI bus id, expected something like 0000:0a:00.0")
+            });
+        }
+
+        Ok(Selector::Name(s.into()))
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Index(index) => write!(f, "{index}"),
+            Selector::Uuid(uuid) => write!(f, "{uuid}"),
+            Selector::PciBusId(domain, bus, device, function) => {
+                write!(f, "{domain:08x}:{bus:02x}:{device:02x}.{function:x}")
+            }
+            Selector::Name(pattern) => write!(f, "{pattern}"),
+        }
+    }
+}
+
+fn is_uuid(s: &str) -> bool {
+    ["GPU-", "MIG-"].iter().any(|prefix| {
+        s.get(..prefix.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+    })
+}
+
+/// Whether two PCI bus ids are the same device, NVML isn't consistent about case or how many
+/// digits the domain gets.
+pub fn same_bus_id(a: &str, b: &str) -> bool {
+    parse_bus_id(a).is_some_and(|a| parse_bus_id(b) == Some(a))
+}
+
+/// A PCI bus id as sysfs names the device, i.e `0000:3b:00.0` for `00000000:3B:00.0`.
+pub fn sysfs_bus_id(s: &str) -> Option<String> {
+    match parse_bus_id(s)? {
+        Selector::PciBusId(domain, bus, device, function) => {
+            Some(format!("{domain:04x}:{bus:02x}:{device:02x}.{function:x}"))
+        }
+        _ => None,
+    }
+}
+
+/// `0000:0a:00.0`, `00000000:0A:00.0` or `0a:00.0`, the domain is 0 when it's left out.
+fn parse_bus_id(s: &str) -> Option<Selector> {
+    let (rest, function) = s.trim().rsplit_once('.')?;
+    let mut parts = rest.rsplit(':');
+    let device = u8::from_str_radix(parts.next()?, 16).ok()?;
+    let bus = u8::from_str_radix(parts.next()?, 16).ok()?;
+    let domain = match parts.next() {
+        Some(domain) => u32::from_str_radix(domain, 16).ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(Selector::PciBusId(
+        domain,
+        bus,
+        device,
+        u8::from_str_radix(function, 16).ok()?,
+    ))
+}
+
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob(rest, &text[skip..])),
+        Some((&c, rest)) => match text.split_first() {
+            Some((&t, text)) if c == '?' || c == t => glob(rest, text),
+            _ => false,
+        },
+    }
+}
+
+/// Which devices to show, from `--devices`, `devices = "..."` in the config or the
+/// `*_VISIBLE_DEVICES` environment variables, i.e `0,2`, `GPU-5b3c,0000:0a:00.0` or `*A100*`.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum DeviceFilter {
+    #[default]
+    All,
+    /// Devices matching any of these, none at all when it's empty.
+    Only(Vec<Selector>),
+}
+
+impl DeviceFilter {
+    /// Environment variables listing the devices a job's been given, by index or UUID.
+    pub const VISIBLE_DEVICES: [&'static str; 2] =
+        ["CUDA_VISIBLE_DEVICES", "NVIDIA_VISIBLE_DEVICES"];
+
+    pub fn matches(&self, id: &Identity) -> bool {
+        match self {
+            DeviceFilter::All => true,
+            DeviceFilter::Only(selectors) => selectors.iter().any(|s| s.matches(id)),
+        }
+    }
+
+    /// A filter for each of [`Self::VISIBLE_DEVICES`] that's set.
+    pub fn from_env() -> Vec<(&'static str, Self)> {
+        Self::VISIBLE_DEVICES
+            .into_iter()
+            .filter_map(|var| Some((var, Self::from_visible_devices(&std::env::var(var).ok()?))))
+            .collect()
+    }
+
+    /// Parse a `*_VISIBLE_DEVICES` value the way CUDA and the NVIDIA container runtime do:
+    /// `all`, `none`/`void`, or a list of indices and UUIDs that ends at the first one that's
+    /// neither, so `CUDA_VISIBLE_DEVICES=-1` hides everything.
+    pub fn from_visible_devices(value: &str) -> Self {
+        match value.trim() {
+            "all" => DeviceFilter::All,
+            "none" | "void" => DeviceFilter::Only(vec![]),
+            list => DeviceFilter::Only(
+                list.split(',')
+                    .map_while(|entry| Selector::parse_visible(entry.trim()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl FromStr for DeviceFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" | "all" => Ok(DeviceFilter::All),
+            list => Ok(DeviceFilter::Only(
+                list.split(',').map(str::parse).collect::<Result<_, _>>()?,
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for DeviceFilter {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for DeviceFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceFilter::All => write!(f, "all"),
+            DeviceFilter::Only(selectors) if selectors.is_empty() => write!(f, "none"),
+            DeviceFilter::Only(selectors) => {
+                let list = selectors.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+                write!(f, "{}", list.join(","))
+            }
+        }
+    }
+}
+
+impl From<DeviceFilter> for String {
+    fn from(filter: DeviceFilter) -> Self {
+        filter.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A100: Identity = Identity {
+        index: 2,
+        uuid: "GPU-5b3c6d2e-1f4a-7b8c-9d0e-123456789abc",
+        pci_bus_id: "00000000:0A:00.0",
+        name: "NVIDIA A100-SXM4-40GB",
+    };
+
+    fn matches(filter: &str) -> bool {
+        filter.parse::<DeviceFilter>().unwrap().matches(&A100)
+    }
+
+    #[test]
+    fn select_devices() {
+        for yes in [
+            "all",
+            "0,2",
+            "gpu-5b3c",
+            "GPU-5b3c6d2e-1f4a-7b8c-9d0e-123456789abc",
+            "0000:0a:00.0",
+            "0a:00.0",
+            "*a100*",
+            "NVIDIA A100-SXM4-??GB",
+            "1, *A100*",
+        ] {
+            assert!(matches(yes), "{yes}");
+        }
+        for no in ["0", "GPU-5b3d", "0b:00.0", "*3090*", "A100"] {
+            assert!(!matches(no), "{no}");
+        }
+
+        assert!("0a:00".parse::<DeviceFilter>().is_err());
+        assert!("0,,1".parse::<DeviceFilter>().is_err());
+        assert_eq!(
+            "2,0000:0a:00.0,*A100*"
+                .parse::<DeviceFilter>()
+                .unwrap()
+                .to_string(),
+            "2,00000000:0a:00.0,*A100*"
+        );
+    }
+
+    #[test]
+    fn bus_ids() {
+        assert!(same_bus_id("00000000:3B:00.0", "0000:3b:00.0"));
+        assert!(same_bus_id("00000000:3B:00.0", "3b:00.0"));
+        assert!(!same_bus_id("00000000:3B:00.0", "00000000:3B:00.1"));
+        assert!(!same_bus_id("nonsense", "nonsense"));
+        assert_eq!(
+            sysfs_bus_id("00000000:3B:00.0").as_deref(),
+            Some("0000:3b:00.0")
+        );
+    }
+
+    #[test]
+    fn visible_devices() {
+        let visible = |value| DeviceFilter::from_visible_devices(value).matches(&A100);
+
+        assert!(visible("all"));
+        assert!(visible("2,3"));
+        assert!(visible("GPU-5b3c"));
+        assert!(!visible("none"));
+        assert!(!visible(""));
+        assert!(!visible("-1"));
+        // Everything after the first bad entry is ignored, like CUDA does.
+        assert!(!visible("0,-1,2"));
+    }
+}
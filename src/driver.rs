@@ -0,0 +1,94 @@
+use std::fmt;
+
+use nvml_wrapper::{error::NvmlError, Nvml};
+
+/// A CUDA version as NVML reports it, `1000 * major + 10 * minor`, i.e `11080` is 11.8 and `12020`
+/// is 12.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct CudaVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl CudaVersion {
+    pub fn from_nvml(version: i32) -> Self {
+        let version = version.max(0) as u32;
+        Self {
+            major: version / 1000,
+            minor: version % 1000 / 10,
+        }
+    }
+}
+
+impl fmt::Display for CudaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// The versions of everything between us and the GPUs, the same for every device on the machine.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DriverInfo {
+    /// i.e `535.104.05`
+    pub driver_version: String,
+    /// i.e `12.535.104.05`
+    pub nvml_version: String,
+    /// The newest CUDA the driver supports.
+    pub cuda_version: CudaVersion,
+}
+
+impl DriverInfo {
+    pub fn read(nvml: &Nvml) -> Result<Self, NvmlError> {
+        Ok(Self {
+            driver_version: nvml.sys_driver_version()?,
+            nvml_version: nvml.sys_nvml_version()?,
+            cuda_version: CudaVersion::from_nvml(nvml.sys_cuda_driver_version()?),
+        })
+    }
+
+    /// A label and value for each version, for the info panel.
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Driver version", self.driver_version.clone()),
+            ("NVML version", self.nvml_version.clone()),
+            ("CUDA version", self.cuda_version.to_string()),
+        ]
+    }
+}
+
+/// i.e `driver 535.104.05, NVML 12.535.104.05, CUDA 12.2`
+impl fmt::Display for DriverInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "driver {}, NVML {}, CUDA {}",
+            self.driver_version, self.nvml_version, self.cuda_version
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cuda_versions() {
+        let version = |v| CudaVersion::from_nvml(v).to_string();
+
+        assert_eq!(version(11080), "11.8");
+        assert_eq!(version(12020), "12.2");
+        assert_eq!(version(10010), "10.1");
+        assert_eq!(version(12000), "12.0");
+        assert!(CudaVersion::from_nvml(11080) < CudaVersion::from_nvml(12000));
+
+        let driver = DriverInfo {
+            driver_version: "535.104.05".into(),
+            nvml_version: "12.535.104.05".into(),
+            cuda_version: CudaVersion::from_nvml(12020),
+        };
+        assert_eq!(
+            driver.to_string(),
+            "driver 535.104.05, NVML 12.535.104.05, CUDA 12.2"
+        );
+    }
+}
@@ -6,10 +6,15 @@ use thiserror::Error;
 pub enum NvTopError {
     Nvml(#[from] nvml_wrapper::error::NvmlError),
     Io(#[from] std::io::Error),
+    /// A config file we couldn't read or parse, the message says where and why.
+    Config(String),
 }
 
 impl Display for NvTopError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:#?}")
+        match self {
+            NvTopError::Config(msg) => write!(f, "{msg}"),
+            _ => write!(f, "{self:#?}"),
+        }
     }
 }
@@ -10,49 +10,92 @@ use nvml_wrapper::{
     Device, Nvml,
 };
 
-use crate::{errors::NvTopError, termite::LoggingHandle};
+use crate::{
+    capabilities::Capabilities,
+    config::Config,
+    details::DeviceDetails,
+    devices::{DeviceFilter, Identity},
+    driver::DriverInfo,
+    errors::NvTopError,
+    health::is_device_error,
+    mig::MigInstance,
+    termite::LoggingHandle,
+};
 
 #[derive(Debug)]
 pub struct GpuInfo<'d> {
+    /// NVML's index, this can change from one rescan to the next, use [`GpuInfo::uuid`] to tell
+    /// devices apart.
     pub index: u32,
+    pub uuid: String,
+    pub pci_bus_id: String,
     pub inner: Device<'d>,
-    pub max_memory_clock: u32,
-    pub max_core_clock: u32,
+    /// What this device can tell us, widgets for anything else are left out.
+    pub capabilities: Capabilities,
+    /// MHz, `None` where the device doesn't say.
+    pub max_memory_clock: Option<u32>,
+    /// MHz, `None` where the device doesn't say.
+    pub max_core_clock: Option<u32>,
     pub card_type: String,
-    pub driver_version: String,
-    pub cuda_version: f32,
-    pub misc: String,
-    pub num_cores: u32,
+    pub driver: DriverInfo,
+    /// For the info panel.
+    pub details: DeviceDetails,
+    /// Empty unless MIG is on.
+    pub mig: Vec<MigInstance<'d>>,
 }
 
 impl<'d> GpuInfo<'d> {
     pub fn from_device(index: u32, device: Device<'d>) -> Result<Self, NvmlError> {
         // Do some setup for things that will _not_ change, i.e driver version etc.
-        let card_type = format!("{:?}", device.brand()?);
-        let driver_version = device.nvml().sys_driver_version()?;
-        let cuda_version = device.nvml().sys_cuda_driver_version()? as f32;
-
-        let misc = format!(
-            "Card: {:?}    Driver Version: {}    CUDA Version: {}",
-            card_type,
-            driver_version,
-            cuda_version / 1000.0
-        );
+        let card_type = optional(device.brand())?.map_or("Unknown".into(), |b| format!("{b:?}"));
+        let driver = DriverInfo::read(device.nvml())?;
+        let details = DeviceDetails::read(&device);
+        let mig = match details.mig_mode {
+            Some(mode) if mode.current => optional(MigInstance::enumerate(&device))?,
+            _ => None,
+        };
 
         Ok(GpuInfo {
-            max_memory_clock: device.max_clock_info(Clock::Memory)?,
-            max_core_clock: device.max_clock_info(Clock::Graphics)?,
-            num_cores: device.num_cores()?,
+            uuid: device.uuid()?,
+            pci_bus_id: optional(device.pci_info())?.map_or(String::new(), |pci| pci.bus_id),
+            capabilities: Capabilities::probe(&device),
+            max_memory_clock: optional(device.max_clock_info(Clock::Memory))?,
+            max_core_clock: optional(device.max_clock_info(Clock::Graphics))?,
+            details,
+            mig: mig.unwrap_or_default(),
             card_type,
-            driver_version,
-            cuda_version,
-            misc,
+            driver,
             index,
             inner: device,
         })
     }
 }
 
+impl GpuInfo<'_> {
+    /// Label and value for each line of the info panel.
+    pub fn info_rows(&self, cfg: &Config) -> Vec<(&'static str, String)> {
+        let mut rows = self.details.rows(cfg);
+        rows.push(("Brand", self.card_type.clone()));
+        rows.extend(self.driver.rows());
+        rows.extend(self.mig.iter().map(|instance| {
+            let id = instance.id();
+            let profile = instance.profile();
+            ("MIG instance", format!("{id} {profile} {}", instance.uuid))
+        }));
+        rows
+    }
+}
+
+/// `None` for anything the device can't or won't tell us, only failing when the device itself is
+/// in trouble.
+fn optional<T>(result: Result<T, NvmlError>) -> Result<Option<T>, NvmlError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if is_device_error(&e) => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
 impl<'d> Deref for GpuInfo<'d> {
     type Target = Device<'d>;
 
@@ -63,13 +106,18 @@ impl<'d> Deref for GpuInfo<'d> {
 
 impl fmt::Display for GpuInfo<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let meminfo = self.inner.memory_info().unwrap();
-        let utilisation = self.inner.utilization_rates().unwrap();
         writeln!(f, "Brand: {:?}", self.inner.brand())?;
-        writeln!(f, "core: {:?}%", utilisation.gpu)?;
-        writeln!(f, "mem_used: {:?}", meminfo.used as f64 / 1_073_741_824.0)?;
-        writeln!(f, "mem {:?}%", (meminfo.total / meminfo.used))?;
-        writeln!(f, "mem_total: {:?}", meminfo.total as f64 / 1_073_741_824.0)?;
+        match self.inner.utilization_rates() {
+            Ok(utilisation) => writeln!(f, "core: {:?}%", utilisation.gpu)?,
+            Err(e) => writeln!(f, "core: {e}")?,
+        }
+        match self.inner.memory_info() {
+            Ok(meminfo) => {
+                writeln!(f, "mem_used: {:?}", meminfo.used as f64 / 1_073_741_824.0)?;
+                writeln!(f, "mem_total: {:?}", meminfo.total as f64 / 1_073_741_824.0)?;
+            }
+            Err(e) => writeln!(f, "mem: {e}")?,
+        }
         writeln!(
             f,
             "Temp: {:?}C",
@@ -105,40 +153,73 @@ impl fmt::Display for GpuInfo<'_> {
     }
 }
 
+/// Every device matching all of `filters`, see [`crate::config::Config::device_filters`].
 pub fn try_init_gpus<'n>(
     nvml: &'n Nvml,
+    filters: &[(&str, DeviceFilter)],
     lh: &LoggingHandle,
 ) -> Result<Vec<GpuInfo<'n>>, NvTopError> {
     let count = nvml.device_count()?;
     let mut gpu_list = Vec::with_capacity(count as usize);
+    let mut selected = 0;
 
     for i in 0..count {
         match nvml.device_by_index(i) {
             Ok(dev) => {
-                let gpu = GpuInfo::from_device(i, dev)?;
-                lh.error(&format!("Compatible GPU found at [{i}]: {gpu}"));
-                gpu_list.push(gpu);
-            }
-            Err(
-                _err @ (NvmlError::InsufficientPower
-                | NvmlError::NoPermission
-                | NvmlError::IrqIssue
-                | NvmlError::GpuLost),
-            ) => {
-                lh.error("Failed to init device [{i}]: {err}");
-                continue; // carry on
+                if let Some((source, _)) = filters.iter().find(|(_, f)| !f.matches_device(i, &dev))
+                {
+                    lh.debug(&format!("Skipping device [{i}], not selected by {source}"));
+                    continue;
+                }
+                selected += 1;
+
+                match GpuInfo::from_device(i, dev) {
+                    Ok(gpu) => {
+                        lh.error(&format!("Compatible GPU found at [{i}]: {gpu}"));
+                        gpu_list.push(gpu);
+                    }
+                    Err(e) => lh.error(&format!("Failed to init device [{i}]: {e}")),
+                }
             }
-            Err(e) => return Err(e.into()),
+            // One bad device shouldn't stop us showing the rest.
+            Err(e) => lh.error(&format!("Failed to init device [{i}]: {e}")),
         }
     }
 
-    if gpu_list.is_empty() {
+    if selected == 0 && count > 0 && !filters.is_empty() {
+        let filters = filters
+            .iter()
+            .map(|(source, filter)| format!("{source}={filter}"))
+            .collect::<Vec<_>>();
+        Err(NvTopError::Config(format!(
+            "None of the {count} GPUs match {}",
+            filters.join(" and ")
+        )))
+    } else if gpu_list.is_empty() {
         Err(io::Error::new(ErrorKind::NotFound, "No compatible GPU detected").into())
     } else {
         Ok(gpu_list)
     }
 }
 
+impl DeviceFilter {
+    pub(crate) fn matches_device(&self, index: u32, device: &Device) -> bool {
+        if *self == DeviceFilter::All {
+            return true;
+        }
+
+        let uuid = device.uuid().unwrap_or_default();
+        let pci_bus_id = device.pci_info().map(|pci| pci.bus_id).unwrap_or_default();
+        let name = device.name().unwrap_or_default();
+        self.matches(&Identity {
+            index,
+            uuid: &uuid,
+            pci_bus_id: &pci_bus_id,
+            name: &name,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use nvml_wrapper::{
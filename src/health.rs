@@ -0,0 +1,164 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use nvml_wrapper::{error::NvmlError, Nvml};
+
+use crate::{gpu::GpuInfo, sample::Sample, termite::LoggingHandle};
+
+/// Errors that mean the device itself is in trouble, as opposed to it not supporting something.
+pub fn is_device_error(e: &NvmlError) -> bool {
+    matches!(
+        e,
+        NvmlError::GpuLost
+            | NvmlError::ResetRequired
+            | NvmlError::IrqIssue
+            | NvmlError::InsufficientPower
+            | NvmlError::Timeout
+            | NvmlError::Uninitialized
+            | NvmlError::Unknown
+    )
+}
+
+/// How a device has been doing, kept alongside each [`GpuInfo`] so one falling off the bus
+/// doesn't take the others down with it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Health {
+    #[default]
+    Healthy,
+    /// Stopped answering, we try to get it back every [`Health::RETRY`].
+    Lost {
+        error: String,
+        since: SystemTime,
+        last_attempt: Instant,
+    },
+}
+
+impl Health {
+    pub const RETRY: Duration = Duration::from_secs(5);
+
+    fn lost(error: &NvmlError) -> Self {
+        Health::Lost {
+            error: error.to_string(),
+            since: SystemTime::now(),
+            last_attempt: Instant::now(),
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        *self == Health::Healthy
+    }
+
+    /// i.e `lost at 14:02:11 UTC (2m 5s ago): the GPU has fallen off the bus`, `None` while it's fine.
+    pub fn describe(&self) -> Option<String> {
+        let Health::Lost { error, since, .. } = self else {
+            return None;
+        };
+
+        let ago = since.elapsed().unwrap_or_default().as_secs();
+        let ago = match ago {
+            0..=59 => format!("{ago}s"),
+            60..=3599 => format!("{}m {}s", ago / 60, ago % 60),
+            _ => format!("{}h {}m", ago / 3600, ago % 3600 / 60),
+        };
+        Some(format!(
+            "lost at {} ({ago} ago): {error}",
+            utc_clock(*since)
+        ))
+    }
+}
+
+/// `HH:MM:SS UTC`, there's no timezone database to hand.
+fn utc_clock(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        % 86_400;
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Sample every device that's answering, marking any that stop as lost, and try to re-attach lost
+/// ones every [`Health::RETRY`] by UUID, as their index may well have changed. Lost devices get a
+/// [`Sample::missing`] so their history keeps in step with everyone else's.
+pub fn sample_devices<'n>(
+    nvml: &'n Nvml,
+    gpu_list: &mut [GpuInfo<'n>],
+    health: &mut [Health],
+    lh: &LoggingHandle,
+) -> Vec<Sample> {
+    gpu_list
+        .iter_mut()
+        .zip(health.iter_mut())
+        .map(|(gpu, health)| {
+            if let Health::Lost { last_attempt, .. } = health {
+                if last_attempt.elapsed() < Health::RETRY {
+                    return Sample::missing();
+                }
+                *last_attempt = Instant::now();
+
+                match reattach(nvml, &gpu.uuid) {
+                    Ok(found) => {
+                        lh.info(&format!(
+                            "GPU [{}] {} is back as [{}]",
+                            gpu.index, gpu.uuid, found.index
+                        ));
+                        *gpu = found;
+                        *health = Health::Healthy;
+                    }
+                    Err(e) => {
+                        lh.debug(&format!("GPU [{}] {} still lost: {e}", gpu.index, gpu.uuid));
+                        return Sample::missing();
+                    }
+                }
+            }
+
+            match Sample::take(gpu) {
+                Ok(sample) => sample,
+                Err(e) => {
+                    lh.error(&format!("Lost GPU [{}] {}: {e}", gpu.index, gpu.uuid));
+                    *health = Health::lost(&e);
+                    Sample::missing()
+                }
+            }
+        })
+        .collect()
+}
+
+fn reattach<'n>(nvml: &'n Nvml, uuid: &str) -> Result<GpuInfo<'n>, NvmlError> {
+    let device = nvml.device_by_uuid(uuid)?;
+    let gpu = GpuInfo::from_device(device.index()?, device)?;
+    // Only call it back once it's answering again.
+    Sample::take(&gpu)?;
+
+    Ok(gpu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_lost_devices() {
+        assert_eq!(Health::Healthy.describe(), None);
+
+        let lost = Health::Lost {
+            error: NvmlError::GpuLost.to_string(),
+            since: UNIX_EPOCH + Duration::from_secs(86_400 * 365 + 14 * 3600 + 2 * 60 + 11),
+            last_attempt: Instant::now(),
+        };
+        let described = lost.describe().unwrap();
+        assert!(
+            described.starts_with("lost at 14:02:11 UTC ("),
+            "{described}"
+        );
+        assert!(described.ends_with(&NvmlError::GpuLost.to_string()));
+        assert!(!lost.is_healthy());
+
+        assert!(is_device_error(&NvmlError::GpuLost));
+        assert!(!is_device_error(&NvmlError::NotSupported));
+    }
+}
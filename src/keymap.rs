@@ -0,0 +1,465 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Everything a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    /// Stop and return to the shell, `fg` to come back.
+    Suspend,
+    NextDevice,
+    PrevDevice,
+    Rescan,
+    /// Switch between the gauges for one device and a table of all of them.
+    ToggleView,
+    /// Switch to (or back from) how the GPUs are connected to each other, and how busy their
+    /// NVLinks are.
+    ToggleLinks,
+    /// Switch to (or back from) the vGPUs running on the selected device.
+    ToggleVgpus,
+    /// Freeze the display on the current samples, sampling carries on in the background.
+    Pause,
+    StepBack,
+    StepForward,
+    /// Cycle through the named layouts.
+    NextLayout,
+    /// Move the highlight to the next panel.
+    FocusNext,
+    /// Blow the highlighted panel up to fill the screen, or put it back.
+    Zoom,
+    /// Scroll the device info panel.
+    ScrollUp,
+    ScrollDown,
+    Settings,
+    Help,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::Quit,
+        Action::Suspend,
+        Action::NextDevice,
+        Action::PrevDevice,
+        Action::Rescan,
+        Action::ToggleView,
+        Action::ToggleLinks,
+        Action::ToggleVgpus,
+        Action::Pause,
+        Action::StepBack,
+        Action::StepForward,
+        Action::NextLayout,
+        Action::FocusNext,
+        Action::Zoom,
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::Settings,
+        Action::Help,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Suspend => "suspend",
+            Action::NextDevice => "next device",
+            Action::PrevDevice => "previous device",
+            Action::Rescan => "rescan devices",
+            Action::ToggleView => "toggle device table",
+            Action::ToggleLinks => "toggle topology/NVLink view",
+            Action::ToggleVgpus => "toggle vGPU view",
+            Action::Pause => "freeze/resume",
+            Action::StepBack => "previous sample",
+            Action::StepForward => "next sample",
+            Action::NextLayout => "next layout",
+            Action::FocusNext => "highlight next panel",
+            Action::Zoom => "zoom highlighted panel",
+            Action::ScrollUp => "scroll device info up",
+            Action::ScrollDown => "scroll device info down",
+            Action::Settings => "settings",
+            Action::Help => "help",
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q", "ctrl-c"],
+            Action::Suspend => &["ctrl-z"],
+            Action::NextDevice => &["tab", "right"],
+            Action::PrevDevice => &["shift-tab", "left"],
+            Action::Rescan => &["p"],
+            Action::ToggleView => &["v"],
+            Action::ToggleLinks => &["n"],
+            Action::ToggleVgpus => &["g"],
+            Action::Pause => &["space"],
+            Action::StepBack => &[","],
+            Action::StepForward => &["."],
+            Action::NextLayout => &["l"],
+            Action::FocusNext => &["f"],
+            Action::Zoom => &["z", "enter"],
+            Action::ScrollUp => &["up", "k"],
+            Action::ScrollDown => &["down", "j"],
+            Action::Settings => &["s"],
+            Action::Help => &["?"],
+        }
+    }
+}
+
+/// Keys the event loop handles itself rather than through the [`Keymap`], as they stand for a
+/// whole range of devices or always mean "back". The help overlay lists them from here too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixedKey {
+    /// Type a device's index.
+    Digit(char),
+    /// `F1` is device 0 and so on.
+    Function(u8),
+    Escape,
+}
+
+impl FixedKey {
+    /// One of each, for the help overlay.
+    pub const ALL: [FixedKey; 3] = [
+        FixedKey::Digit('0'),
+        FixedKey::Function(1),
+        FixedKey::Escape,
+    ];
+
+    pub fn from_code(code: KeyCode) -> Option<Self> {
+        match code {
+            KeyCode::Char(c @ '0'..='9') => Some(FixedKey::Digit(c)),
+            KeyCode::F(n) => Some(FixedKey::Function(n)),
+            KeyCode::Esc => Some(FixedKey::Escape),
+            _ => None,
+        }
+    }
+
+    /// The keys of this kind, as shown in the help overlay.
+    pub fn keys(&self) -> &'static str {
+        match self {
+            FixedKey::Digit(_) => "0-9",
+            FixedKey::Function(_) => "f1-f12",
+            FixedKey::Escape => "esc",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            FixedKey::Digit(_) => "type a device index",
+            FixedKey::Function(_) => "select device by index + 1",
+            FixedKey::Escape => "close help, zoom or settings",
+        }
+    }
+}
+
+/// A key plus modifiers, written like `q`, `ctrl-c`, `shift-tab`, `f5` or `space` in the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        // Shift is already baked into the character (or BackTab) crossterm gives us.
+        let ignore = match event.code {
+            KeyCode::Char(_) | KeyCode::BackTab => KeyModifiers::SHIFT,
+            _ => KeyModifiers::NONE,
+        };
+
+        event.code == self.code && event.modifiers - ignore == self.modifiers - ignore
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = s;
+
+        // A lone '-' is the minus key, not a separator.
+        while let Some((modifier, rest)) = key.split_once('-').filter(|(_, rest)| !rest.is_empty())
+        {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => return Err(format!("unknown modifier '{other}' in key '{s}'")),
+            };
+            key = rest;
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => {
+                    modifiers -= KeyModifiers::SHIFT;
+                    KeyCode::BackTab
+                }
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                f if f.starts_with('f') => f[1..]
+                    .parse()
+                    .ok()
+                    .filter(|n| (1..=24).contains(n))
+                    .map(KeyCode::F)
+                    .ok_or_else(|| format!("unknown key '{key}'"))?,
+                _ => return Err(format!("unknown key '{key}'")),
+            },
+        };
+
+        Ok(KeyBinding { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl-"),
+            (KeyModifiers::ALT, "alt-"),
+            (KeyModifiers::SHIFT, "shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            KeyCode::BackTab => f.write_str("shift-tab"),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            other => write!(f, "{}", format!("{other:?}").to_ascii_lowercase()),
+        }
+    }
+}
+
+impl Serialize for KeyBinding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Maps key presses to [`Action`]s, in the config file each action takes a key or a list of keys,
+/// replacing its defaults:
+/// ```toml
+/// [keys]
+/// quit = ["x", "ctrl-c"]
+/// pause = "p"
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: BTreeMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL
+                .into_iter()
+                .map(|action| {
+                    let keys = action
+                        .default_keys()
+                        .iter()
+                        .map(|k| k.parse().expect("default key bindings are valid"))
+                        .collect();
+                    (action, keys)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn action_for(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|k| k.matches(event)))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn keys_for(&self, action: Action) -> &[KeyBinding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replace the keys bound to `action`, taking them away from anything else they were bound to.
+    pub fn bind(&mut self, action: Action, keys: Vec<KeyBinding>) {
+        self.bindings
+            .values_mut()
+            .for_each(|bound| bound.retain(|k| !keys.contains(k)));
+        self.bindings.insert(action, keys);
+    }
+
+    /// The keys for `action` as they'd be written in the config, i.e `q, ctrl-c`.
+    pub fn describe(&self, action: Action) -> String {
+        self.keys_for(action)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// One line hint for the bottom of the screen.
+    pub fn footer(&self) -> String {
+        [Action::Quit, Action::Rescan, Action::Settings, Action::Help]
+            .into_iter()
+            .filter_map(|action| {
+                self.keys_for(action)
+                    .first()
+                    .map(|key| format!("{key} {}", action.description()))
+            })
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+}
+
+impl Serialize for Keymap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bindings.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(KeyBinding),
+            Many(Vec<KeyBinding>),
+        }
+
+        let mut keymap = Keymap::default();
+        for (action, keys) in BTreeMap::<Action, OneOrMany>::deserialize(deserializer)? {
+            keymap.bind(
+                action,
+                match keys {
+                    OneOrMany::One(key) => vec![key],
+                    OneOrMany::Many(keys) => keys,
+                },
+            );
+        }
+
+        Ok(keymap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        for key in [
+            "q",
+            "ctrl-c",
+            "shift-tab",
+            "f5",
+            "space",
+            "?",
+            "-",
+            "alt-enter",
+            "pagedown",
+        ] {
+            assert_eq!(key.parse::<KeyBinding>().unwrap().to_string(), key);
+        }
+        assert!("hyper-x".parse::<KeyBinding>().is_err());
+        assert!("f99".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn default_bindings() {
+        let keymap = Keymap::default();
+        let action = |code, modifiers| keymap.action_for(&press(code, modifiers));
+
+        assert_eq!(
+            action(KeyCode::Char('q'), KeyModifiers::NONE),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            action(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Some(Action::Quit)
+        );
+        assert_eq!(action(KeyCode::Char('c'), KeyModifiers::NONE), None);
+        assert_eq!(
+            action(KeyCode::BackTab, KeyModifiers::SHIFT),
+            Some(Action::PrevDevice)
+        );
+        assert_eq!(
+            action(KeyCode::Char('?'), KeyModifiers::SHIFT),
+            Some(Action::Help)
+        );
+    }
+
+    #[test]
+    fn fixed_keys() {
+        assert_eq!(
+            FixedKey::from_code(KeyCode::Char('7')),
+            Some(FixedKey::Digit('7'))
+        );
+        assert_eq!(
+            FixedKey::from_code(KeyCode::F(3)),
+            Some(FixedKey::Function(3))
+        );
+        assert_eq!(FixedKey::from_code(KeyCode::Char('a')), None);
+        // Each listed in the help is one the event loop handles.
+        for key in FixedKey::ALL {
+            let code = match key {
+                FixedKey::Digit(c) => KeyCode::Char(c),
+                FixedKey::Function(n) => KeyCode::F(n),
+                FixedKey::Escape => KeyCode::Esc,
+            };
+            assert_eq!(FixedKey::from_code(code), Some(key), "{}", key.keys());
+        }
+    }
+
+    #[test]
+    fn overrides_replace_defaults() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            keys: Keymap,
+        }
+
+        let keymap = toml::from_str::<Wrapper>(
+            "[keys]\nquit = \"x\"\nhelp = [\"h\", \"f1\"]\npause = \"p\"\n",
+        )
+        .unwrap()
+        .keys;
+
+        assert_eq!(keymap.describe(Action::Quit), "x");
+        assert_eq!(keymap.describe(Action::Help), "h, f1");
+        assert_eq!(keymap.describe(Action::Pause), "p");
+        // 'p' was taken from rescan.
+        assert_eq!(keymap.describe(Action::Rescan), "");
+        assert_eq!(keymap.describe(Action::Settings), "s");
+
+        assert!(toml::from_str::<Wrapper>("[keys]\nfly = \"x\"\n").is_err());
+    }
+}
@@ -0,0 +1,327 @@
+use ratatui::prelude::{Constraint, Direction, Layout, Rect};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, Panel, Panels};
+
+/// One cell of a dashboard: a widget, or rows/columns of more cells. `size` is the percentage of
+/// its parent a cell takes up, cells without one share whatever's left, i.e:
+/// ```toml
+/// [layouts.wide]
+/// columns = [
+///   { size = 40, widget = "core-utilisation" },
+///   { rows = [{ widget = "memory" }, { widget = "temperature" }, { widget = "fan" }] },
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawNode", into = "RawNode")]
+pub struct LayoutNode {
+    pub size: Option<u16>,
+    pub content: Content,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    Widget(Panel),
+    Rows(Vec<LayoutNode>),
+    Columns(Vec<LayoutNode>),
+}
+
+impl LayoutNode {
+    pub fn widget(panel: Panel, size: Option<u16>) -> Self {
+        Self {
+            size,
+            content: Content::Widget(panel),
+        }
+    }
+
+    /// The widgets in this layout, in the order they're drawn.
+    pub fn widgets(&self) -> Vec<Panel> {
+        match &self.content {
+            Content::Widget(panel) => vec![*panel],
+            Content::Rows(children) | Content::Columns(children) => {
+                children.iter().flat_map(LayoutNode::widgets).collect()
+            }
+        }
+    }
+
+    fn is_visible(&self, panels: &Panels) -> bool {
+        self.widgets().into_iter().any(|p| panels.is_visible(p))
+    }
+
+    /// Work out where each visible widget goes within `area`, hidden widgets give up their space.
+    pub fn place(&self, area: Rect, panels: &Panels) -> Vec<(Panel, Rect)> {
+        let mut placed = vec![];
+        self.place_into(area, panels, 0, &mut placed);
+        placed
+    }
+
+    fn place_into(&self, area: Rect, panels: &Panels, margin: u16, out: &mut Vec<(Panel, Rect)>) {
+        let (direction, children) = match &self.content {
+            Content::Widget(panel) => {
+                if panels.is_visible(*panel) {
+                    out.push((*panel, area));
+                }
+                return;
+            }
+            Content::Rows(children) => (Direction::Vertical, children),
+            Content::Columns(children) => (Direction::Horizontal, children),
+        };
+
+        // Cells without a size split what the others leave over.
+        let sized = children.iter().filter_map(|c| c.size).sum::<u16>();
+        let unsized_count = children.iter().filter(|c| c.size.is_none()).count() as u16;
+        let share = (100u16.saturating_sub(sized) / unsized_count.max(1)).max(1);
+
+        let visible = children
+            .iter()
+            .filter(|c| c.is_visible(panels))
+            .map(|c| (c, u32::from(c.size.unwrap_or(share))))
+            .collect::<Vec<_>>();
+        let total = visible.iter().map(|(_, weight)| weight).sum::<u32>();
+        if total == 0 {
+            return;
+        }
+
+        let chunks = Layout::default()
+            .direction(direction)
+            .margin(margin)
+            .constraints(
+                visible
+                    .iter()
+                    .map(|(_, weight)| Constraint::Ratio(*weight, total))
+                    .collect::<Vec<_>>(),
+            )
+            .split(area);
+
+        for ((child, _), area) in visible.into_iter().zip(chunks.iter()) {
+            child.place_into(*area, panels, 1, out);
+        }
+    }
+}
+
+/// What's written in the config, checked before becoming a [`LayoutNode`].
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawNode {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    widget: Option<Panel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rows: Option<Vec<LayoutNode>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    columns: Option<Vec<LayoutNode>>,
+}
+
+impl TryFrom<RawNode> for LayoutNode {
+    type Error = String;
+
+    fn try_from(raw: RawNode) -> Result<Self, Self::Error> {
+        if raw.size.is_some_and(|size| size > 100) {
+            return Err("size is a percentage and must be within 0..=100".into());
+        }
+
+        let content = match (raw.widget, raw.rows, raw.columns) {
+            (Some(panel), None, None) => Content::Widget(panel),
+            (None, Some(children), None) => Content::Rows(check_children(children)?),
+            (None, None, Some(children)) => Content::Columns(check_children(children)?),
+            _ => return Err("a layout cell needs exactly one of widget, rows or columns".into()),
+        };
+
+        Ok(Self {
+            size: raw.size,
+            content,
+        })
+    }
+}
+
+fn check_children(children: Vec<LayoutNode>) -> Result<Vec<LayoutNode>, String> {
+    if children.is_empty() {
+        return Err("rows and columns need at least one cell".into());
+    }
+
+    let total = children
+        .iter()
+        .filter_map(|c| c.size.map(u32::from))
+        .sum::<u32>();
+    if total > 100 {
+        return Err(format!("sizes add up to {total}, which is more than 100"));
+    }
+
+    Ok(children)
+}
+
+impl From<LayoutNode> for RawNode {
+    fn from(node: LayoutNode) -> Self {
+        let mut raw = RawNode {
+            size: node.size,
+            widget: None,
+            rows: None,
+            columns: None,
+        };
+        match node.content {
+            Content::Widget(panel) => raw.widget = Some(panel),
+            Content::Rows(children) => raw.rows = Some(children),
+            Content::Columns(children) => raw.columns = Some(children),
+        }
+        raw
+    }
+}
+
+/// The named layouts to cycle through: `default` (from `[layout]`), `compact` and any from
+/// `[layouts.<name>]`, which can also replace those two.
+#[derive(Debug, Clone)]
+pub struct Layouts {
+    named: Vec<(String, LayoutNode)>,
+    current: usize,
+    /// Terminals narrower or shorter than this get `compact` whatever's selected.
+    compact_below: (u16, u16),
+}
+
+impl Layouts {
+    pub const DEFAULT: &'static str = "default";
+    pub const COMPACT: &'static str = "compact";
+
+    pub fn from_config(cfg: &Config) -> Self {
+        let mut named = vec![
+            (Self::DEFAULT.to_string(), cfg.layout.to_node()),
+            (Self::COMPACT.to_string(), compact()),
+        ];
+        for (name, node) in &cfg.layouts {
+            match named.iter_mut().find(|(n, _)| n == name) {
+                Some((_, existing)) => *existing = node.clone(),
+                None => named.push((name.clone(), node.clone())),
+            }
+        }
+
+        let current = named
+            .iter()
+            .position(|(name, _)| *name == cfg.layout.start)
+            // Checked when the config's loaded, only a hand built one can miss.
+            .unwrap_or(0);
+
+        Self {
+            named,
+            current,
+            compact_below: (cfg.layout.compact_below[0], cfg.layout.compact_below[1]),
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.named.len();
+    }
+
+    /// The layout to use in `area`, along with its name.
+    pub fn for_area(&self, area: Rect) -> (&str, &LayoutNode) {
+        let (width, height) = self.compact_below;
+        let (name, node) = match area.width < width || area.height < height {
+            true => self
+                .named
+                .iter()
+                .find(|(name, _)| name == Self::COMPACT)
+                .unwrap_or(&self.named[self.current]),
+            false => &self.named[self.current],
+        };
+
+        (name, node)
+    }
+
+    /// The selected layout, regardless of the terminal size.
+    pub fn current(&self) -> (&str, &LayoutNode) {
+        let (name, node) = &self.named[self.current];
+        (name, node)
+    }
+}
+
+/// The gauges that matter most, stacked without any margins.
+fn compact() -> LayoutNode {
+    LayoutNode {
+        size: None,
+        content: Content::Rows(
+            [Panel::CoreUtilisation, Panel::Memory, Panel::Temperature]
+                .map(|panel| LayoutNode::widget(panel, None))
+                .to_vec(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_layouts() {
+        let cfg = Config::from_toml(
+            r#"
+            [layout]
+            start = "wide"
+
+            [layouts.wide]
+            columns = [
+                { size = 40, widget = "core-utilisation" },
+                { rows = [{ widget = "memory" }, { widget = "fan", size = 20 }] },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let layouts = Layouts::from_config(&cfg);
+        let (name, wide) = layouts.current();
+        assert_eq!(name, "wide");
+        assert_eq!(
+            wide.widgets(),
+            [Panel::CoreUtilisation, Panel::Memory, Panel::Fan]
+        );
+
+        for bad in [
+            "[layouts.x]\nwidget = \"memory\"\nrows = []\n",
+            "[layouts.x]\nrows = []\n",
+            "[layouts.x]\nrows = [{ widget = \"memory\", size = 70 }, { widget = \"fan\", size = 70 }]\n",
+            "[layouts.x]\nwidget = \"gpu-go-brr\"\n",
+            "[layouts.x]\nrows = [{ widget = \"memory\", size = 65535 }, { widget = \"fan\", size = 2 }]\n",
+            "[layout]\nstart = \"y\"\n\n[layouts.x]\nwidget = \"memory\"\n",
+        ] {
+            assert!(Config::from_toml(bad).is_err(), "{bad}");
+        }
+
+        let err =
+            Config::from_toml("[layout]\nstart = \"y\"\n\n[layouts.x]\nwidget = \"memory\"\n")
+                .unwrap_err()
+                .to_string();
+        assert!(err.contains("\"y\""), "{err}");
+        assert!(err.contains("default, compact, x"), "{err}");
+    }
+
+    #[test]
+    fn place_splits_by_size_and_skips_hidden() {
+        let node = LayoutNode {
+            size: None,
+            content: Content::Columns(vec![
+                LayoutNode::widget(Panel::CoreUtilisation, Some(50)),
+                LayoutNode::widget(Panel::Memory, None),
+                LayoutNode::widget(Panel::Fan, None),
+            ]),
+        };
+        let area = Rect::new(0, 0, 100, 10);
+
+        let placed = node.place(area, &Panels::default());
+        let widths = placed.iter().map(|(_, r)| r.width).collect::<Vec<_>>();
+        assert_eq!(widths, [50, 25, 25]);
+
+        let panels = Panels {
+            fan: false,
+            ..Default::default()
+        };
+        let placed = node.place(area, &panels);
+        assert_eq!(placed.len(), 2);
+        assert_eq!(placed[1], (Panel::Memory, Rect::new(67, 0, 33, 10)));
+    }
+
+    #[test]
+    fn small_terminals_get_compact() {
+        let layouts = Layouts::from_config(&Config::default());
+
+        assert_eq!(layouts.for_area(Rect::new(0, 0, 120, 40)).0, "default");
+        assert_eq!(layouts.for_area(Rect::new(0, 0, 40, 10)).0, "compact");
+    }
+}
@@ -1,6 +1,30 @@
+pub mod alerts;
 pub mod app;
+pub mod capabilities;
+pub mod compact;
+pub mod config;
+pub mod details;
+pub mod devices;
+pub mod driver;
 pub mod errors;
 pub mod gpu;
+pub mod health;
+pub mod keymap;
+pub mod layout;
+pub mod mig;
+#[cfg(feature = "dbus")]
+pub mod notify;
+pub mod nvlink;
 pub mod nvtop_args;
+pub mod raw;
+pub mod rescan;
+pub mod sample;
+pub mod settings;
+pub mod status;
 pub mod stylers;
+pub mod terminal;
 pub mod termite;
+pub mod theme;
+pub mod topo;
+pub mod vgpu;
+pub mod zoom;
@@ -4,7 +4,18 @@ use anyhow::Result;
 use clap::Parser;
 use nvml_wrapper::Nvml;
 
-use nvtop::{app::run, errors::NvTopError, nvtop_args, termite::LoggingHandle};
+use nvtop::{
+    alerts::{AlertActions, Alerter},
+    app::run,
+    capabilities, compact,
+    config::Config,
+    errors::NvTopError,
+    gpu::try_init_gpus,
+    nvtop_args::{self, Command},
+    status,
+    termite::LoggingHandle,
+    topo,
+};
 
 fn main() -> Result<(), NvTopError> {
     let args = nvtop_args::Cli::parse();
@@ -18,12 +29,81 @@ fn main() -> Result<(), NvTopError> {
         lh = LoggingHandle::init(log_path);
     }
 
+    let config_path = args.config.clone().or_else(Config::default_path);
+    let mut config = match Config::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    // What the settings overlay saves, without the one-off flags below.
+    let file_config = config.clone();
+    if let Some(delay) = args.delay {
+        config.delay = delay;
+    }
+    if let Some(theme) = args.theme {
+        config.theme = theme;
+    }
+    if let Some(devices) = args.devices.clone() {
+        config.devices = devices;
+    }
+    if args.visible_devices {
+        config.visible_devices = true;
+    }
+    if let Some(rescan) = args.rescan {
+        config.rescan = rescan;
+    }
+    if args.no_mouse {
+        config.mouse = false;
+    }
+
     // Init the GPU management-layer
     let nvml = Nvml::init()?;
     lh.debug("Nvml init success");
 
-    if let Err(e) = run(nvml, Duration::from_millis(args.delay), &lh) {
+    let mut alerter = Alerter::new(
+        args.alert,
+        AlertActions {
+            command: args.alert_command,
+            webhook: args.alert_webhook,
+            cooldown: Duration::from_secs(args.alert_cooldown),
+            retries: args.alert_retries,
+            #[cfg(feature = "dbus")]
+            desktop_notify: args.notify,
+        },
+        &lh,
+    );
+
+    match &args.command {
+        Some(Command::Status(status_args)) => return status::run(nvml, &config, status_args, &lh),
+        Some(Command::Topo) => return topo::run(nvml, &config, &lh),
+        None => {}
+    }
+
+    if args.capabilities {
+        let gpu_list = try_init_gpus(&nvml, &config.device_filters(), &lh)?;
+        let devices = gpu_list
+            .iter()
+            .map(|gpu| {
+                let name = gpu.name().unwrap_or_else(|_| gpu.card_type.clone());
+                (format!("[{}] {name}", gpu.index), &gpu.capabilities)
+            })
+            .collect::<Vec<_>>();
+        if let Some(gpu) = gpu_list.first() {
+            println!("{}\n", gpu.driver);
+        }
+        println!("{}", capabilities::matrix(&devices));
+        return Ok(());
+    }
+
+    if args.compact {
+        return compact::run(nvml, &config, &lh, &mut alerter);
+    }
+
+    if let Err(e) = run(nvml, config, file_config, config_path, &lh, &mut alerter) {
         lh.error(&format!("app::run() -> {e}"));
+        eprintln!("nvtop: {e}");
     }
 
     Ok(())
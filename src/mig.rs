@@ -0,0 +1,98 @@
+use std::{collections::HashMap, time::Instant};
+
+use nvml_wrapper::{error::NvmlError, Device};
+
+use crate::{gpu::GpuInfo, health::Health, raw, sample::Sample};
+
+/// A slice of a GPU with Multi-Instance GPU on, i.e one compute instance in one of its GPU
+/// instances. NVML hands these out as devices of their own, though most queries aren't supported
+/// on them.
+#[derive(Debug)]
+pub struct MigInstance<'d> {
+    pub gpu_instance: u32,
+    pub compute_instance: u32,
+    pub uuid: String,
+    /// i.e `NVIDIA A100-SXM4-40GB MIG 1g.5gb`
+    pub name: String,
+    pub inner: Device<'d>,
+}
+
+impl<'d> MigInstance<'d> {
+    /// Every instance on `device`, ordered by GPU then compute instance.
+    pub fn enumerate(device: &Device<'d>) -> Result<Vec<Self>, NvmlError> {
+        let mut instances = raw::mig_devices(device)?
+            .into_iter()
+            .map(|inner| {
+                Ok(Self {
+                    gpu_instance: raw::gpu_instance_id(&inner)?,
+                    compute_instance: raw::compute_instance_id(&inner)?,
+                    uuid: inner.uuid()?,
+                    name: inner.name().unwrap_or_default(),
+                    inner,
+                })
+            })
+            .collect::<Result<Vec<_>, NvmlError>>()?;
+        instances.sort_by_key(|i| (i.gpu_instance, i.compute_instance));
+
+        Ok(instances)
+    }
+
+    /// `{gpu instance}/{compute instance}`, i.e `1/0`
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.gpu_instance, self.compute_instance)
+    }
+
+    /// The instance under its parent's index, i.e `0/1/0`, the same shape CUDA's
+    /// `MIG-GPU-<uuid>/1/0` names take.
+    pub fn label(&self, parent: u32) -> String {
+        format!("{parent}/{}", self.id())
+    }
+
+    pub fn profile(&self) -> &str {
+        profile(&self.name)
+    }
+
+    /// Memory and utilisation, where NVML gives them out per instance, everything else is the
+    /// parent's to report.
+    pub fn sample(&self) -> Sample {
+        let memory = self.inner.memory_info().ok();
+
+        Sample {
+            taken: Instant::now(),
+            utilisation: self.inner.utilization_rates().ok().map(|ur| ur.gpu),
+            memory_used: memory.as_ref().map(|mi| mi.used),
+            memory_total: memory.as_ref().map(|mi| mi.total),
+            memory_free: memory.as_ref().map(|mi| mi.free),
+            ..Sample::missing()
+        }
+    }
+}
+
+/// i.e `1g.5gb` from `NVIDIA A100-SXM4-40GB MIG 1g.5gb`, or the whole name if it isn't in the
+/// usual form.
+pub fn profile(name: &str) -> &str {
+    name.split_once("MIG ").map_or(name, |(_, profile)| profile)
+}
+
+/// A sample of every instance on every working device, by instance UUID.
+pub fn sample_instances(gpu_list: &[GpuInfo], health: &[Health]) -> HashMap<String, Sample> {
+    gpu_list
+        .iter()
+        .zip(health)
+        .filter(|(_, health)| health.is_healthy())
+        .flat_map(|(gpu, _)| &gpu.mig)
+        .map(|instance| (instance.uuid.clone(), instance.sample()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_from_names() {
+        assert_eq!(profile("NVIDIA A100-SXM4-40GB MIG 1g.5gb"), "1g.5gb");
+        assert_eq!(profile("NVIDIA H100 80GB HBM3 MIG 3g.40gb"), "3g.40gb");
+        assert_eq!(profile("Something else"), "Something else");
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use zbus::{blocking::Connection, zvariant::Value};
+
+use crate::alerts::{AlertEvent, Crossing, Metric};
+
+/// Sends freedesktop desktop notifications (org.freedesktop.Notifications) for [`AlertEvent`]s.
+pub struct DesktopNotifier {
+    conn: Connection,
+    /// Minimum time between two notifications for the same device and metric, regardless of direction,
+    /// so a metric flapping around its threshold doesn't bury the desktop in popups.
+    throttle: Duration,
+    /// By device UUID.
+    last_sent: HashMap<(String, Metric), Instant>,
+}
+
+impl DesktopNotifier {
+    /// Connect to the user's session bus.
+    pub fn session(throttle: Duration) -> zbus::Result<Self> {
+        Ok(Self::with_connection(Connection::session()?, throttle))
+    }
+
+    pub fn with_connection(conn: Connection, throttle: Duration) -> Self {
+        Self {
+            conn,
+            throttle,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// Show a notification for `event`, returns `Ok(false)` if it was throttled.
+    pub fn notify(&mut self, event: &AlertEvent) -> zbus::Result<bool> {
+        let key = (event.uuid.clone(), event.metric);
+        if let Some(when) = self.last_sent.get(&key) {
+            if when.elapsed() < self.throttle {
+                return Ok(false);
+            }
+        }
+
+        let summary = format!(
+            "GPU {} {} {} {}",
+            event.device, event.metric, event.state, event.threshold
+        );
+        let body = format!("{} is now {:.1}", event.metric, event.value);
+
+        // https://specifications.freedesktop.org/notification-spec/latest/urgency-levels.html
+        let urgency: u8 = match event.state {
+            Crossing::Above => 2,
+            Crossing::Below => 1,
+        };
+        let hints = HashMap::from([("urgency", Value::from(urgency))]);
+
+        self.conn.call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                "nvtop",
+                0u32,
+                "",
+                summary.as_str(),
+                body.as_str(),
+                Vec::<&str>::new(),
+                hints,
+                -1i32,
+            ),
+        )?;
+
+        self.last_sent.insert(key, Instant::now());
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Command, Stdio},
+        sync::{Arc, Mutex},
+    };
+    use zbus::{blocking::ConnectionBuilder, dbus_interface, zvariant::OwnedValue};
+
+    struct FakeNotificationServer {
+        received: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.Notifications")]
+    impl FakeNotificationServer {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &mut self,
+            _app_name: String,
+            _replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            body: String,
+            _actions: Vec<String>,
+            _hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let mut received = self.received.lock().unwrap();
+            received.push((summary, body));
+            received.len() as u32
+        }
+    }
+
+    #[ignore = "needs dbus-daemon on the PATH"]
+    #[test]
+    fn notifies_over_private_session_bus() {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+
+        let received = Arc::new(Mutex::new(vec![]));
+        let _server = ConnectionBuilder::address(address.trim())
+            .unwrap()
+            .name("org.freedesktop.Notifications")
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/Notifications",
+                FakeNotificationServer {
+                    received: received.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let client = ConnectionBuilder::address(address.trim())
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut notifier = DesktopNotifier::with_connection(client, Duration::from_secs(60));
+
+        let mut event = AlertEvent {
+            device: 0,
+            uuid: "GPU-5ad2b1e8-0d3c-4a25-8a4b-0c6b1d5e6f70".into(),
+            metric: Metric::Temperature,
+            value: 91.0,
+            threshold: 85.0,
+            state: Crossing::Above,
+            timestamp: 0,
+        };
+        let sent = notifier.notify(&event);
+
+        // A flapping metric is throttled.
+        event.state = Crossing::Below;
+        let throttled = notifier.notify(&event);
+
+        _ = daemon.kill();
+        _ = daemon.wait();
+
+        assert!(sent.unwrap());
+        assert!(!throttled.unwrap());
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![(
+                "GPU 0 temperature above 85".to_string(),
+                "temperature is now 91.0".to_string()
+            )]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::time::Instant;
+
+use nvml_wrapper::{enum_wrappers::nv_link::UtilizationCountUnit, enums::nv_link::Counter, Device};
+use nvml_wrapper_sys::bindings::NVML_NVLINK_MAX_LINKS;
+
+use crate::{config::MemoryUnit, devices::same_bus_id};
+
+/// One NVLink on a device as it was when we looked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkReading {
+    pub link: u32,
+    pub active: bool,
+    pub version: Option<u32>,
+    /// Whatever's on the other end, another GPU or an NVSwitch.
+    pub remote_bus_id: Option<String>,
+    /// What counter 0 counts, `None` until someone's set it up with
+    /// `nvidia-smi nvlink --setcontrol`, there's no default.
+    pub units: Option<UtilizationCountUnit>,
+    /// Running totals of counter 0, received and sent.
+    pub counter: Option<(u64, u64)>,
+}
+
+/// Every link a device has, read together so their counters line up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinksSample {
+    pub taken: Instant,
+    pub links: Vec<LinkReading>,
+}
+
+impl LinksSample {
+    /// Links the device doesn't have (or that it won't say anything about) are left out, so this
+    /// is empty for anything without NVLink.
+    pub fn take(device: &Device) -> Self {
+        let links = (0..NVML_NVLINK_MAX_LINKS)
+            .filter_map(|link| {
+                let wrapper = device.link_wrapper_for(link);
+                let active = wrapper.is_active().ok()?;
+
+                Some(LinkReading {
+                    link,
+                    active,
+                    version: wrapper.version().ok(),
+                    remote_bus_id: wrapper.remote_pci_info().ok().map(|pci| pci.bus_id),
+                    units: wrapper
+                        .utilization_control(Counter::Zero)
+                        .ok()
+                        .map(|control| control.units),
+                    counter: wrapper
+                        .utilization_counter(Counter::Zero)
+                        .ok()
+                        .map(|counter| (counter.receive, counter.send)),
+                })
+            })
+            .collect();
+
+        Self {
+            taken: Instant::now(),
+            links,
+        }
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &LinkReading> {
+        self.links.iter().filter(|link| link.active)
+    }
+}
+
+/// The last two samples of a device's links, enough to turn the counters into rates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Links {
+    pub previous: Option<LinksSample>,
+    pub latest: LinksSample,
+}
+
+impl Links {
+    pub fn new(latest: LinksSample) -> Self {
+        Self {
+            previous: None,
+            latest,
+        }
+    }
+
+    pub fn push(&mut self, sample: LinksSample) {
+        self.previous = Some(std::mem::replace(&mut self.latest, sample));
+    }
+
+    /// Received and sent per second on `link` between the last two samples, in whatever the
+    /// counter counts. `None` until there are two samples, or if the counter went backwards, i.e
+    /// it was reset.
+    pub fn rate(&self, link: u32) -> Option<(f64, f64)> {
+        let previous = self.previous.as_ref()?;
+        let counter = |sample: &LinksSample| {
+            sample
+                .links
+                .iter()
+                .find(|reading| reading.link == link)
+                .and_then(|reading| reading.counter)
+        };
+        let (rx_before, tx_before) = counter(previous)?;
+        let (rx, tx) = counter(&self.latest)?;
+        let secs = self
+            .latest
+            .taken
+            .duration_since(previous.taken)
+            .as_secs_f64();
+        if secs == 0.0 {
+            return None;
+        }
+
+        Some((
+            rx.checked_sub(rx_before)? as f64 / secs,
+            tx.checked_sub(tx_before)? as f64 / secs,
+        ))
+    }
+}
+
+/// i.e `1.52 GiB/s`, `3021 pkt/s`
+pub fn format_rate(rate: f64, units: &UtilizationCountUnit, memory: MemoryUnit) -> String {
+    match units {
+        UtilizationCountUnit::Bytes => {
+            format!("{:.2} {}/s", memory.convert(rate as u64), memory.symbol())
+        }
+        UtilizationCountUnit::Packets => format!("{rate:.0} pkt/s"),
+        UtilizationCountUnit::Cycles => format!("{rate:.0} cycles/s"),
+    }
+}
+
+/// How many active links run from each device to each other device, `counts[i][j]` for the
+/// devices at `bus_ids[i]` and `bus_ids[j]`. Links to anything that isn't in `bus_ids`, i.e an
+/// NVSwitch, aren't counted.
+pub fn link_counts(bus_ids: &[&str], links: &[Option<&LinksSample>]) -> Vec<Vec<usize>> {
+    links
+        .iter()
+        .map(|sample| {
+            bus_ids
+                .iter()
+                .map(|other| {
+                    sample.map_or(0, |sample| {
+                        sample
+                            .active()
+                            .filter(|link| {
+                                link.remote_bus_id
+                                    .as_deref()
+                                    .is_some_and(|remote| same_bus_id(remote, other))
+                            })
+                            .count()
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn reading(link: u32, remote: &str, counter: (u64, u64)) -> LinkReading {
+        LinkReading {
+            link,
+            active: true,
+            version: Some(3),
+            remote_bus_id: Some(remote.into()),
+            units: Some(UtilizationCountUnit::Bytes),
+            counter: Some(counter),
+        }
+    }
+
+    #[test]
+    fn counters_become_rates() {
+        let start = Instant::now();
+        let mut links = Links::new(LinksSample {
+            taken: start,
+            links: vec![reading(0, "00000000:3B:00.0", (1_000, 5_000))],
+        });
+        assert_eq!(links.rate(0), None);
+
+        links.push(LinksSample {
+            taken: start + Duration::from_secs(2),
+            links: vec![reading(0, "00000000:3B:00.0", (3_000, 6_000))],
+        });
+        assert_eq!(links.rate(0), Some((1_000.0, 500.0)));
+        assert_eq!(links.rate(1), None);
+
+        // Reset counters don't make for negative rates.
+        links.push(LinksSample {
+            taken: start + Duration::from_secs(3),
+            links: vec![reading(0, "00000000:3B:00.0", (0, 0))],
+        });
+        assert_eq!(links.rate(0), None);
+    }
+
+    #[test]
+    fn count_links_between_devices() {
+        let sample = |remotes: &[&str]| LinksSample {
+            taken: Instant::now(),
+            links: remotes
+                .iter()
+                .enumerate()
+                .map(|(i, remote)| reading(i as u32, remote, (0, 0)))
+                .collect(),
+        };
+        let a = sample(&["00000000:3B:00.0", "00000000:3b:00.0", "00000000:C1:00.0"]);
+        let b = sample(&["0000:1a:00.0", "00000000:1A:00.0"]);
+        let counts = link_counts(
+            &["00000000:1A:00.0", "00000000:3B:00.0", "00000000:5E:00.0"],
+            &[Some(&a), Some(&b), None],
+        );
+
+        // The third link on `a` goes to an NVSwitch.
+        assert_eq!(counts, vec![vec![0, 2, 0], vec![2, 0, 0], vec![0, 0, 0]]);
+    }
+}
@@ -1,17 +1,117 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+
+use crate::{alerts::Threshold, config::Config, devices::DeviceFilter, theme::ThemeName};
 
 #[derive(Parser)]
 pub struct Cli {
-    /// Amount of time to wait in millis.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// Amount of time to wait in millis, [default: 100] unless set in the config file.
     /// nvtop --delay 1000  # to run with a delay of 1s.
     /// nvtop -d 200        # short flags are supported.
-    #[clap(short, long, value_name = "MILLISECONDS", default_value_t = 100)]
-    pub delay: u64,
+    #[clap(short, long, value_name = "MILLISECONDS", value_parser = clap::value_parser!(u64).range(Config::MIN_DELAY..))]
+    pub delay: Option<u64>,
+
+    /// Path to a config file, by default `$XDG_CONFIG_HOME/nvtop/config.toml` is used if it exists.
+    /// Flags given on the command line take precedence over the config file.
+    #[clap(short, long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Colour theme, falls back to monochrome when `NO_COLOR` is set.
+    #[clap(long, value_enum)]
+    pub theme: Option<ThemeName>,
+
+    /// Only show these devices, a comma separated list of indices, UUIDs, PCI bus ids or name globs, i.e:
+    /// `nvtop --devices 0,2` or `nvtop --devices GPU-5b3c,0000:0a:00.0` or `nvtop --devices '*A100*'`
+    #[clap(long, value_name = "LIST")]
+    pub devices: Option<DeviceFilter>,
+
+    /// Only show the devices listed in CUDA_VISIBLE_DEVICES and NVIDIA_VISIBLE_DEVICES, when set.
+    /// Indices are NVML's, which follow the PCI bus order, as does CUDA with CUDA_DEVICE_ORDER=PCI_BUS_ID.
+    #[clap(long)]
+    pub visible_devices: bool,
+
+    /// Seconds between checks for GPUs being added or removed, 0 to only rescan when `p` is pressed.
+    /// [default: 5] unless set in the config file.
+    #[clap(long, value_name = "SECONDS")]
+    pub rescan: Option<u64>,
+
+    /// Print which metrics each GPU supports and exit, anything unsupported is left out of the UI.
+    #[clap(long)]
+    pub capabilities: bool,
+
+    /// Print a line per GPU, updated in place, instead of the full UI. Handy for a small split pane.
+    #[clap(long)]
+    pub compact: bool,
+
+    /// Leave the mouse to the terminal, for terminals that misbehave with mouse capture.
+    #[clap(long)]
+    pub no_mouse: bool,
 
     /// Enable logging to DISK, disabled by default, requires a path that you want to log to, i.e:
     /// `nvtop --log ~/Documents/nvtop.log`
     #[clap(long, value_name = "Enable Logging")]
     pub log: Option<PathBuf>,
+
+    /// Alert when a metric crosses a threshold, may be given multiple times, i.e:
+    /// `nvtop --alert temperature=85 --alert memory=90`
+    /// metrics are: temperature (°C), memory (% used), utilisation (%) and fan (%).
+    #[clap(long, value_name = "METRIC=VALUE")]
+    pub alert: Vec<Threshold>,
+
+    /// Shell command to run when an alert fires, the details are passed in the environment as
+    /// NVTOP_DEVICE, NVTOP_UUID, NVTOP_METRIC, NVTOP_VALUE, NVTOP_THRESHOLD and NVTOP_STATE (above|below).
+    #[clap(long, value_name = "COMMAND")]
+    pub alert_command: Option<String>,
+
+    /// URL to POST a JSON payload to when an alert fires.
+    #[clap(long, value_name = "URL")]
+    pub alert_webhook: Option<String>,
+
+    /// Minimum number of seconds between two alerts for the same device, metric and direction.
+    #[clap(long, value_name = "SECONDS", default_value_t = 60)]
+    pub alert_cooldown: u64,
+
+    /// How many times to retry a failing alert command or webhook.
+    #[clap(long, value_name = "COUNT", default_value_t = 3, value_parser = clap::value_parser!(u32).range(0..=10))]
+    pub alert_retries: u32,
+
+    /// Show a desktop notification when an alert fires, these are throttled per device and metric by `--alert-cooldown`.
+    #[cfg(feature = "dbus")]
+    #[clap(long)]
+    pub notify: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print a one line summary of each GPU and exit, i.e for a tmux, i3 or waybar status line.
+    Status(StatusArgs),
+    /// Print how the GPUs are connected to each other (NVLink or how far apart they are on PCIe)
+    /// and which CPUs and NUMA node each is closest to, like `nvidia-smi topo -m`.
+    Topo,
+}
+
+#[derive(Args)]
+pub struct StatusArgs {
+    /// Template for each GPU, placeholders are: index, uuid, pci_bus_id, name, utilisation, temperature,
+    /// temperature_unit, memory_used, memory_free, memory_total, memory_reserved, memory_percent,
+    /// memory_unit, memory_bandwidth, bar1_used, bar1_total, fan, core_clock, max_core_clock, driver_version, nvml_version and cuda_version. Numbers take a width and precision, i.e `{memory_used:.1}`.
+    /// [default: "GPU{index} {utilisation}% {temperature}{temperature_unit} {memory_used:.1}/{memory_total:.0}{memory_unit}"]
+    #[clap(short, long, value_name = "TEMPLATE")]
+    pub format: Option<String>,
+
+    /// Put between each GPU's summary.
+    #[clap(long, default_value = " | ")]
+    pub separator: String,
+
+    /// Keep going, printing a new line every interval rather than exiting.
+    #[clap(short, long, value_name = "MILLISECONDS", value_parser = clap::value_parser!(u64).range(Config::MIN_DELAY..))]
+    pub interval: Option<u64>,
+
+    /// Write to this file instead of stdout, replacing it each time.
+    #[clap(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
 }
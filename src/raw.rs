@@ -0,0 +1,308 @@
+//! NVML calls nvml-wrapper doesn't cover yet, made straight against the library through
+//! nvml-wrapper-sys.
+
+use std::{
+    ffi::{c_char, c_uint, CStr},
+    mem, ptr,
+    sync::OnceLock,
+};
+
+use nvml_wrapper::{
+    error::{nvml_sym, nvml_try, NvmlError},
+    struct_wrappers::device::MemoryInfo as MemoryInfoV1,
+    Device,
+};
+use nvml_wrapper_sys::bindings::{
+    nvmlMemory_v2_t, nvmlReturn_t, nvmlValueType_enum_NVML_VALUE_TYPE_DOUBLE,
+    nvmlValueType_enum_NVML_VALUE_TYPE_UNSIGNED_INT,
+    nvmlValueType_enum_NVML_VALUE_TYPE_UNSIGNED_LONG,
+    nvmlValueType_enum_NVML_VALUE_TYPE_UNSIGNED_LONG_LONG, nvmlValueType_t, nvmlValue_t,
+    nvmlVgpuInstanceUtilizationSample_t, NvmlLib, NVML_DEVICE_MIG_ENABLE,
+    NVML_DEVICE_UUID_BUFFER_SIZE, NVML_VGPU_NAME_BUFFER_SIZE,
+};
+
+use crate::vgpu::VirtualizationMode;
+
+#[cfg(target_os = "windows")]
+const LIB_PATH: &str = "nvml.dll";
+#[cfg(not(target_os = "windows"))]
+const LIB_PATH: &str = "libnvidia-ml.so";
+
+/// The library nvml-wrapper has already loaded and initialised, loaded again for its raw symbols.
+/// Both share the one copy in memory, so device handles from either work with the other.
+fn lib() -> Result<&'static NvmlLib, NvmlError> {
+    static LIB: OnceLock<Option<NvmlLib>> = OnceLock::new();
+
+    LIB.get_or_init(|| unsafe { NvmlLib::new(LIB_PATH) }.ok())
+        .as_ref()
+        .ok_or(NvmlError::LibraryNotFound)
+}
+
+/// Whether Multi-Instance GPU is on, and whether it will be after the next reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigMode {
+    pub current: bool,
+    pub pending: bool,
+}
+
+pub fn mig_mode(device: &Device) -> Result<MigMode, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlDeviceGetMigMode.as_ref())?;
+    let (mut current, mut pending) = (0, 0);
+
+    unsafe { nvml_try(sym(device.handle(), &mut current, &mut pending))? };
+
+    Ok(MigMode {
+        current: current == NVML_DEVICE_MIG_ENABLE,
+        pending: pending == NVML_DEVICE_MIG_ENABLE,
+    })
+}
+
+/// Bytes of device memory, with what the driver keeps for itself split out of `used`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryInfo {
+    pub total: u64,
+    /// `None` where only the first version of the call is available, which counts it as used.
+    pub reserved: Option<u64>,
+    pub free: u64,
+    pub used: u64,
+}
+
+impl From<MemoryInfoV1> for MemoryInfo {
+    fn from(memory: MemoryInfoV1) -> Self {
+        Self {
+            total: memory.total,
+            reserved: None,
+            free: memory.free,
+            used: memory.used,
+        }
+    }
+}
+
+/// `nvmlDeviceGetMemoryInfo_v2`, which drivers older than 510 don't have.
+pub fn memory_info(device: &Device) -> Result<MemoryInfo, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlDeviceGetMemoryInfo_v2.as_ref())?;
+    let mut memory: nvmlMemory_v2_t = unsafe { mem::zeroed() };
+    // NVML_STRUCT_VERSION(Memory, 2)
+    memory.version = mem::size_of::<nvmlMemory_v2_t>() as c_uint | 2 << 24;
+
+    unsafe { nvml_try(sym(device.handle(), &mut memory))? };
+
+    Ok(MemoryInfo {
+        total: memory.total,
+        reserved: Some(memory.reserved),
+        free: memory.free,
+        used: memory.used,
+    })
+}
+
+/// The MIG devices on `device`, one per compute instance, in no particular order.
+pub fn mig_devices<'n>(device: &Device<'n>) -> Result<Vec<Device<'n>>, NvmlError> {
+    let lib = lib()?;
+    let max_count = nvml_sym(lib.nvmlDeviceGetMaxMigDeviceCount.as_ref())?;
+    let by_index = nvml_sym(lib.nvmlDeviceGetMigDeviceHandleByIndex.as_ref())?;
+    let mut count = 0;
+
+    unsafe { nvml_try(max_count(device.handle(), &mut count))? };
+
+    let mut devices = vec![];
+    for index in 0..count {
+        let mut handle = ptr::null_mut();
+        match unsafe { nvml_try(by_index(device.handle(), index, &mut handle)) } {
+            Ok(()) => devices.push(unsafe { Device::new(handle, device.nvml()) }),
+            // An empty slot.
+            Err(NvmlError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(devices)
+}
+
+/// The GPU instance a MIG device belongs to.
+pub fn gpu_instance_id(mig_device: &Device) -> Result<u32, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlDeviceGetGpuInstanceId.as_ref())?;
+    let mut id = 0;
+
+    unsafe { nvml_try(sym(mig_device.handle(), &mut id))? };
+
+    Ok(id)
+}
+
+/// The compute instance a MIG device is, within its GPU instance.
+pub fn compute_instance_id(mig_device: &Device) -> Result<u32, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlDeviceGetComputeInstanceId.as_ref())?;
+    let mut id = 0;
+
+    unsafe { nvml_try(sym(mig_device.handle(), &mut id))? };
+
+    Ok(id)
+}
+
+pub fn virtualization_mode(device: &Device) -> Result<VirtualizationMode, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlDeviceGetVirtualizationMode.as_ref())?;
+    let mut mode = 0;
+
+    unsafe { nvml_try(sym(device.handle(), &mut mode))? };
+
+    Ok(VirtualizationMode::from_raw(mode))
+}
+
+/// For the calls that fill in an array of ids: ask how many there are, then fetch them.
+fn id_list<F>(call: F) -> Result<Vec<c_uint>, NvmlError>
+where
+    F: Fn(&mut c_uint, *mut c_uint) -> nvmlReturn_t,
+{
+    let mut count = 0;
+    match nvml_try(call(&mut count, ptr::null_mut())) {
+        Ok(()) => return Ok(vec![]),
+        Err(NvmlError::InsufficientSize(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut ids = vec![0; count as usize];
+    nvml_try(call(&mut count, ids.as_mut_ptr()))?;
+    ids.truncate(count as usize);
+
+    Ok(ids)
+}
+
+/// The vGPU type ids the device can host.
+pub fn supported_vgpu_types(device: &Device) -> Result<Vec<u32>, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlDeviceGetSupportedVgpus.as_ref())?;
+    let handle = unsafe { device.handle() };
+
+    id_list(|count, ids| unsafe { sym(handle, count, ids) })
+}
+
+/// The vGPU instances running on the device, only on a vGPU host.
+pub fn active_vgpus(device: &Device) -> Result<Vec<u32>, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlDeviceGetActiveVgpus.as_ref())?;
+    let handle = unsafe { device.handle() };
+
+    id_list(|count, ids| unsafe { sym(handle, count, ids) })
+}
+
+/// For the calls that fill in a string, `size` is what's passed in as the buffer's length.
+fn string<F>(size: u32, call: F) -> Result<String, NvmlError>
+where
+    F: FnOnce(*mut c_char, c_uint) -> nvmlReturn_t,
+{
+    let mut buffer = vec![0 as c_char; size as usize];
+    nvml_try(call(buffer.as_mut_ptr(), size))?;
+
+    Ok(unsafe { CStr::from_ptr(buffer.as_ptr()) }
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// i.e `GRID A100-4C`
+pub fn vgpu_type_name(type_id: u32) -> Result<String, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlVgpuTypeGetName.as_ref())?;
+
+    string(NVML_VGPU_NAME_BUFFER_SIZE, |buffer, size| {
+        let mut size = size;
+        unsafe { sym(type_id, buffer, &mut size) }
+    })
+}
+
+/// Bytes
+pub fn vgpu_type_framebuffer(type_id: u32) -> Result<u64, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlVgpuTypeGetFramebufferSize.as_ref())?;
+    let mut size = 0;
+
+    unsafe { nvml_try(sym(type_id, &mut size))? };
+
+    Ok(size)
+}
+
+pub fn vgpu_instance_type(instance: u32) -> Result<u32, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlVgpuInstanceGetType.as_ref())?;
+    let mut type_id = 0;
+
+    unsafe { nvml_try(sym(instance, &mut type_id))? };
+
+    Ok(type_id)
+}
+
+/// The VM the instance is attached to, a domain id or a UUID depending on the hypervisor.
+pub fn vgpu_vm_id(instance: u32) -> Result<String, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlVgpuInstanceGetVmID.as_ref())?;
+
+    string(NVML_DEVICE_UUID_BUFFER_SIZE, |buffer, size| {
+        let mut id_type = 0;
+        unsafe { sym(instance, buffer, size, &mut id_type) }
+    })
+}
+
+pub fn vgpu_uuid(instance: u32) -> Result<String, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlVgpuInstanceGetUUID.as_ref())?;
+
+    string(NVML_DEVICE_UUID_BUFFER_SIZE, |buffer, size| unsafe {
+        sym(instance, buffer, size)
+    })
+}
+
+/// Bytes of frame buffer the instance is using.
+pub fn vgpu_fb_usage(instance: u32) -> Result<u64, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlVgpuInstanceGetFbUsage.as_ref())?;
+    let mut usage = 0;
+
+    unsafe { nvml_try(sym(instance, &mut usage))? };
+
+    Ok(usage)
+}
+
+/// The latest utilisation of a vGPU instance, in %.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VgpuUtilisation {
+    pub instance: u32,
+    pub sm: Option<f64>,
+    pub memory: Option<f64>,
+}
+
+/// One for each vGPU instance on the device.
+pub fn vgpu_utilisation(device: &Device) -> Result<Vec<VgpuUtilisation>, NvmlError> {
+    let sym = nvml_sym(lib()?.nvmlDeviceGetVgpuUtilization.as_ref())?;
+    let handle = unsafe { device.handle() };
+    let mut value_type: nvmlValueType_t = 0;
+    let mut count = 0;
+
+    match unsafe { nvml_try(sym(handle, 0, &mut value_type, &mut count, ptr::null_mut())) } {
+        Ok(()) => return Ok(vec![]),
+        Err(NvmlError::InsufficientSize(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut samples: Vec<nvmlVgpuInstanceUtilizationSample_t> =
+        vec![unsafe { mem::zeroed() }; count as usize];
+    unsafe {
+        nvml_try(sym(
+            handle,
+            0,
+            &mut value_type,
+            &mut count,
+            samples.as_mut_ptr(),
+        ))?
+    };
+    samples.truncate(count as usize);
+
+    let value = |value: nvmlValue_t| unsafe {
+        #[allow(non_upper_case_globals)]
+        match value_type {
+            nvmlValueType_enum_NVML_VALUE_TYPE_DOUBLE => Some(value.dVal),
+            nvmlValueType_enum_NVML_VALUE_TYPE_UNSIGNED_INT => Some(f64::from(value.uiVal)),
+            nvmlValueType_enum_NVML_VALUE_TYPE_UNSIGNED_LONG => Some(value.ulVal as f64),
+            nvmlValueType_enum_NVML_VALUE_TYPE_UNSIGNED_LONG_LONG => Some(value.ullVal as f64),
+            _ => None,
+        }
+    };
+
+    Ok(samples
+        .into_iter()
+        .map(|sample| VgpuUtilisation {
+            instance: sample.vgpuInstance,
+            sm: value(sample.smUtil),
+            memory: value(sample.memUtil),
+        })
+        .collect())
+}
@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+
+use nvml_wrapper::{error::NvmlError, Nvml};
+
+use crate::{
+    config::Config,
+    devices::DeviceFilter,
+    errors::NvTopError,
+    gpu::{try_init_gpus, GpuInfo},
+    health::Health,
+    sample::History,
+    termite::LoggingHandle,
+};
+
+/// The UUIDs of every device matching `filters`, cheap enough to poll for devices coming and going.
+pub fn device_uuids(
+    nvml: &Nvml,
+    filters: &[(&str, DeviceFilter)],
+) -> Result<Vec<String>, NvmlError> {
+    let count = nvml.device_count()?;
+
+    Ok((0..count)
+        .filter_map(|i| {
+            let device = nvml.device_by_index(i).ok()?;
+            match filters.iter().all(|(_, f)| f.matches_device(i, &device)) {
+                true => device.uuid().ok(),
+                false => None,
+            }
+        })
+        .collect())
+}
+
+/// Whether `found` has a device we don't know about, or is missing one that was working. Lost
+/// devices going missing don't count, they're already greyed out and being retried.
+pub fn needs_rescan<'a>(
+    known: impl IntoIterator<Item = (&'a str, &'a Health)>,
+    found: &[String],
+) -> bool {
+    let found = found.iter().map(String::as_str).collect::<HashSet<_>>();
+    let mut known_uuids = HashSet::new();
+
+    for (uuid, health) in known {
+        if health.is_healthy() && !found.contains(uuid) {
+            return true;
+        }
+        known_uuids.insert(uuid);
+    }
+
+    !found.is_subset(&known_uuids)
+}
+
+/// Enumerate the devices again, keeping the history of any we already had (matched by UUID) and
+/// holding on to lost devices so they stay greyed out. Returns a notice saying what changed, if
+/// anything did.
+pub fn rescan<'n>(
+    nvml: &'n Nvml,
+    cfg: &Config,
+    gpu_list: &mut Vec<GpuInfo<'n>>,
+    health: &mut Vec<Health>,
+    histories: &mut Vec<History>,
+    lh: &LoggingHandle,
+) -> Result<Option<String>, NvTopError> {
+    let found = try_init_gpus(nvml, &cfg.device_filters(), lh)?;
+    // New devices are numbered to line up with everyone else, so freezing works across them.
+    let taken = histories.iter().map(History::taken).max().unwrap_or(0);
+
+    let mut old = gpu_list
+        .drain(..)
+        .zip(health.drain(..))
+        .zip(histories.drain(..))
+        .map(|((gpu, health), history)| (gpu, health, history))
+        .collect::<Vec<_>>();
+
+    let mut added = vec![];
+    for gpu in found {
+        let history = match old.iter().position(|(known, ..)| known.uuid == gpu.uuid) {
+            Some(i) => old.swap_remove(i).2,
+            None => {
+                added.push(label(&gpu));
+                History::numbered_from(cfg.history, taken)
+            }
+        };
+        gpu_list.push(gpu);
+        health.push(Health::Healthy);
+        histories.push(history);
+    }
+
+    let mut removed = vec![];
+    for (gpu, was, history) in old {
+        match was {
+            Health::Healthy => removed.push(label(&gpu)),
+            lost => {
+                gpu_list.push(gpu);
+                health.push(lost);
+                histories.push(history);
+            }
+        }
+    }
+
+    let notice = [("added", added), ("removed", removed)]
+        .into_iter()
+        .filter(|(_, devices)| !devices.is_empty())
+        .map(|(what, devices)| format!("{what} {}", devices.join(", ")))
+        .collect::<Vec<_>>();
+    if notice.is_empty() {
+        return Ok(None);
+    }
+
+    let notice = format!("Devices changed: {}", notice.join("; "));
+    lh.info(&notice);
+    Ok(Some(notice))
+}
+
+fn label(gpu: &GpuInfo) -> String {
+    format!(
+        "[{}] {}",
+        gpu.index,
+        gpu.name().unwrap_or_else(|_| gpu.card_type.clone())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Instant, SystemTime};
+
+    use super::*;
+
+    #[test]
+    fn rescan_when_devices_come_and_go() {
+        let healthy = Health::Healthy;
+        let lost = Health::Lost {
+            error: "gone".into(),
+            since: SystemTime::now(),
+            last_attempt: Instant::now(),
+        };
+        let found = |uuids: &[&str]| uuids.iter().map(|u| u.to_string()).collect::<Vec<_>>();
+        let known = [("GPU-a", &healthy), ("GPU-b", &lost)];
+
+        assert!(!needs_rescan(known, &found(&["GPU-a", "GPU-b"])));
+        // Lost devices dropping off the bus are already taken care of.
+        assert!(!needs_rescan(known, &found(&["GPU-a"])));
+        assert!(needs_rescan(known, &found(&["GPU-b"])));
+        assert!(needs_rescan(known, &found(&["GPU-a", "GPU-c"])));
+    }
+}
@@ -0,0 +1,360 @@
+use std::{collections::VecDeque, time::Instant};
+
+use nvml_wrapper::{
+    enum_wrappers::device::{Clock, ClockId, TemperatureSensor},
+    error::NvmlError,
+};
+
+use crate::{
+    alerts::Metric,
+    capabilities::Capability,
+    config::MemoryUnit,
+    gpu::GpuInfo,
+    health::is_device_error,
+    raw::{self, MemoryInfo},
+};
+
+/// One reading of everything we draw for a device, `None` where the device couldn't tell us.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub taken: Instant,
+    /// Core utilisation in %
+    pub utilisation: Option<u32>,
+    /// Bytes
+    pub memory_used: Option<u64>,
+    /// Bytes
+    pub memory_total: Option<u64>,
+    /// Bytes the driver keeps for itself, `None` on drivers that count it as used.
+    pub memory_reserved: Option<u64>,
+    /// Bytes
+    pub memory_free: Option<u64>,
+    /// Memory controller utilisation in %, i.e how busy the memory bus is rather than how full.
+    pub memory_bandwidth: Option<u32>,
+    /// Bytes of BAR1, the window the CPU (and other devices) map device memory through.
+    pub bar1_used: Option<u64>,
+    /// Bytes
+    pub bar1_total: Option<u64>,
+    /// GPU die temperature in °C
+    pub temperature: Option<u32>,
+    /// Average fan speed in %, `None` when there are no fans.
+    pub fan_speed: Option<f64>,
+    /// Graphics clock in MHz
+    pub core_clock: Option<u32>,
+}
+
+impl Sample {
+    /// Read everything from `gpu` that it supports, only failing when the device itself is in
+    /// trouble.
+    pub fn take(gpu: &GpuInfo) -> Result<Self, NvmlError> {
+        let supports = |capability| gpu.capabilities.supports(capability);
+
+        // Every device reports its memory, so this doubles as a check that it's still there.
+        let memory =
+            match raw::memory_info(gpu).or_else(|_| gpu.memory_info().map(MemoryInfo::from)) {
+                Ok(memory) => Some(memory),
+                Err(e) if is_device_error(&e) => return Err(e),
+                Err(_) => None,
+            };
+        let fan_speed = match supports(Capability::Fan) {
+            true => gpu.num_fans().ok().filter(|&n| n != 0).map(|fans| {
+                let total = (0..fans).flat_map(|i| gpu.fan_speed(i)).sum::<u32>();
+                f64::from(total) / f64::from(fans)
+            }),
+            false => None,
+        };
+        let rates = supports(Capability::Utilisation)
+            .then(|| gpu.utilization_rates().ok())
+            .flatten();
+        let bar1 = gpu.bar1_memory_info().ok();
+
+        Ok(Self {
+            taken: Instant::now(),
+            utilisation: rates.as_ref().map(|ur| ur.gpu),
+            memory_used: memory.map(|mi| mi.used),
+            memory_total: memory.map(|mi| mi.total),
+            memory_reserved: memory.and_then(|mi| mi.reserved),
+            memory_free: memory.map(|mi| mi.free),
+            memory_bandwidth: rates.as_ref().map(|ur| ur.memory),
+            bar1_used: bar1.as_ref().map(|b| b.used),
+            bar1_total: bar1.as_ref().map(|b| b.total),
+            temperature: supports(Capability::Temperature)
+                .then(|| gpu.temperature(TemperatureSensor::Gpu).ok())
+                .flatten(),
+            fan_speed,
+            core_clock: supports(Capability::CoreClock)
+                .then(|| gpu.clock(Clock::Graphics, ClockId::Current).ok())
+                .flatten(),
+        })
+    }
+
+    /// Stands in for a device that couldn't be reached.
+    pub fn missing() -> Self {
+        Self {
+            taken: Instant::now(),
+            utilisation: None,
+            memory_used: None,
+            memory_total: None,
+            memory_reserved: None,
+            memory_free: None,
+            memory_bandwidth: None,
+            bar1_used: None,
+            bar1_total: None,
+            temperature: None,
+            fan_speed: None,
+            core_clock: None,
+        }
+    }
+
+    /// The value of `metric` in this sample, in the units [`Metric`] documents.
+    pub fn metric(&self, metric: Metric) -> Option<f64> {
+        match metric {
+            Metric::Temperature => self.temperature.map(f64::from),
+            Metric::Memory => match (self.memory_used, self.memory_total) {
+                (Some(used), Some(total)) if total != 0 => Some(used as f64 / total as f64 * 100.0),
+                _ => None,
+            },
+            Metric::Utilisation => self.utilisation.map(f64::from),
+            Metric::Fan => self.fan_speed,
+        }
+    }
+
+    /// A label and value for each line of the memory breakdown, `N/A` where the device didn't
+    /// say, i.e `("Reserved", "0.40 GiB (1.7%)")`.
+    pub fn memory_rows(&self, units: MemoryUnit) -> Vec<(&'static str, String)> {
+        let of = |bytes: Option<u64>, total: Option<u64>| match (bytes, total) {
+            (Some(bytes), Some(total)) if total != 0 => format!(
+                "{:.2} {} ({:.1}%)",
+                units.convert(bytes),
+                units.symbol(),
+                bytes as f64 / total as f64 * 100.0
+            ),
+            (Some(bytes), _) => format!("{:.2} {}", units.convert(bytes), units.symbol()),
+            (None, _) => "N/A".into(),
+        };
+
+        vec![
+            ("Used", of(self.memory_used, self.memory_total)),
+            ("Reserved", of(self.memory_reserved, self.memory_total)),
+            ("Free", of(self.memory_free, self.memory_total)),
+            (
+                "BAR1",
+                match (self.bar1_used, self.bar1_total) {
+                    (Some(used), Some(total)) => format!(
+                        "{:.2}/{:.2} {}",
+                        units.convert(used),
+                        units.convert(total),
+                        units.symbol()
+                    ),
+                    _ => "N/A".into(),
+                },
+            ),
+            (
+                "Bandwidth",
+                self.memory_bandwidth
+                    .map_or("N/A".into(), |percent| format!("{percent}%")),
+            ),
+        ]
+    }
+}
+
+/// The last `capacity` [`Sample`]s for a device, each numbered in the order it was taken so a
+/// position in the history stays put as new samples arrive.
+#[derive(Debug, Clone)]
+pub struct History {
+    samples: VecDeque<Sample>,
+    capacity: usize,
+    /// How many samples have ever been pushed.
+    taken: u64,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            taken: 0,
+        }
+    }
+
+    /// A history whose first sample will be number `first`, to line up with devices that have
+    /// been sampled for a while.
+    pub fn numbered_from(capacity: usize, first: u64) -> Self {
+        Self {
+            taken: first,
+            ..Self::new(capacity)
+        }
+    }
+
+    /// How many samples have ever been pushed.
+    pub fn taken(&self) -> u64 {
+        self.taken
+    }
+
+    pub fn push(&mut self, sample: Sample) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.taken += 1;
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    /// The number of the newest sample, `None` until one has been taken.
+    pub fn newest(&self) -> Option<u64> {
+        self.taken.checked_sub(1)
+    }
+
+    /// The number of the oldest sample still held.
+    pub fn oldest(&self) -> u64 {
+        self.taken - self.samples.len() as u64
+    }
+
+    /// Sample number `n`, or the nearest one still held if it's fallen out of the history.
+    pub fn get(&self, n: u64) -> Option<&Sample> {
+        let newest = self.newest()?;
+        let n = n.clamp(self.oldest(), newest);
+        self.samples.get((n - self.oldest()) as usize)
+    }
+
+    /// Oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Sample> + ExactSizeIterator {
+        self.samples.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Whether the display follows the newest samples or is frozen on one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Playback {
+    #[default]
+    Live,
+    /// Showing sample number `n`, while sampling carries on behind the scenes.
+    Frozen(u64),
+}
+
+impl Playback {
+    /// Freeze on the newest sample, or go back to following them.
+    pub fn toggle(&mut self, history: &History) {
+        *self = match self {
+            Playback::Live => history.newest().map_or(Playback::Live, Playback::Frozen),
+            Playback::Frozen(_) => Playback::Live,
+        };
+    }
+
+    /// Step `back` (or forward) a sample, freezing first if need be, staying within what's buffered.
+    pub fn step(&mut self, history: &History, back: bool) {
+        let Some(newest) = history.newest() else {
+            return;
+        };
+
+        let current = match self {
+            Playback::Live => newest,
+            Playback::Frozen(n) => (*n).clamp(history.oldest(), newest),
+        };
+
+        *self = Playback::Frozen(match back {
+            true => current.saturating_sub(1).max(history.oldest()),
+            false => (current + 1).min(newest),
+        });
+    }
+
+    /// The sample to draw from `history`.
+    pub fn sample<'h>(&self, history: &'h History) -> Option<&'h Sample> {
+        match self {
+            Playback::Live => history.latest(),
+            Playback::Frozen(n) => history.get(*n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(utilisation: u32) -> Sample {
+        Sample {
+            taken: Instant::now(),
+            utilisation: Some(utilisation),
+            memory_used: Some(1),
+            memory_total: Some(4),
+            ..Sample::missing()
+        }
+    }
+
+    fn utilisation(playback: Playback, history: &History) -> Option<u32> {
+        playback.sample(history).and_then(|s| s.utilisation)
+    }
+
+    #[test]
+    fn history_drops_the_oldest() {
+        let mut history = History::new(3);
+        assert!(history.get(0).is_none());
+
+        (0..5).for_each(|n| history.push(sample(n)));
+
+        assert_eq!(history.len(), 3);
+        assert_eq!((history.oldest(), history.newest()), (2, Some(4)));
+        assert_eq!(history.get(3).unwrap().utilisation, Some(3));
+        // Gone, so the nearest we still have.
+        assert_eq!(history.get(0).unwrap().utilisation, Some(2));
+        assert_eq!(history.latest().unwrap().metric(Metric::Memory), Some(25.0));
+    }
+
+    #[test]
+    fn frozen_playback_stays_put() {
+        let mut history = History::new(10);
+        (0..5).for_each(|n| history.push(sample(n)));
+
+        let mut playback = Playback::default();
+        playback.toggle(&history);
+        history.push(sample(5));
+        assert_eq!(utilisation(playback, &history), Some(4));
+
+        playback.step(&history, true);
+        playback.step(&history, true);
+        assert_eq!(utilisation(playback, &history), Some(2));
+
+        (0..10).for_each(|_| playback.step(&history, false));
+        assert_eq!(playback, Playback::Frozen(5));
+
+        playback.toggle(&history);
+        history.push(sample(6));
+        assert_eq!(utilisation(playback, &history), Some(6));
+
+        // Stepping from live freezes one back.
+        playback.step(&history, true);
+        assert_eq!(utilisation(playback, &history), Some(5));
+    }
+
+    #[test]
+    fn memory_breakdown() {
+        let gib = 1_073_741_824;
+        let sample = Sample {
+            memory_used: Some(6 * gib),
+            memory_total: Some(24 * gib),
+            memory_reserved: Some(gib / 2),
+            memory_free: Some(17 * gib + gib / 2),
+            memory_bandwidth: Some(34),
+            bar1_used: Some(gib / 4),
+            ..Sample::missing()
+        };
+        let rows = sample.memory_rows(MemoryUnit::GiB);
+        let row = |label| rows.iter().find(|(l, _)| *l == label).unwrap().1.as_str();
+
+        assert_eq!(row("Used"), "6.00 GiB (25.0%)");
+        assert_eq!(row("Reserved"), "0.50 GiB (2.1%)");
+        assert_eq!(row("Free"), "17.50 GiB (72.9%)");
+        assert_eq!(row("BAR1"), "N/A", "BAR1 is nothing without its total");
+        assert_eq!(row("Bandwidth"), "34%");
+    }
+}
@@ -0,0 +1,251 @@
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::{
+    app::{centered, Frame},
+    config::{Config, Panel},
+    keymap::Action,
+    theme::Theme,
+};
+
+/// A row in the settings overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Delay,
+    MediumThreshold,
+    HighThreshold,
+    CriticalThreshold,
+    TemperatureUnit,
+    MemoryUnit,
+    Panel(Panel),
+    Save,
+}
+
+impl Setting {
+    pub fn all() -> Vec<Setting> {
+        [
+            Setting::Delay,
+            Setting::MediumThreshold,
+            Setting::HighThreshold,
+            Setting::CriticalThreshold,
+            Setting::TemperatureUnit,
+            Setting::MemoryUnit,
+        ]
+        .into_iter()
+        .chain(Panel::ALL.map(Setting::Panel))
+        .chain([Setting::Save])
+        .collect()
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Setting::Delay => "Refresh interval".into(),
+            Setting::MediumThreshold => "Medium threshold".into(),
+            Setting::HighThreshold => "High threshold".into(),
+            Setting::CriticalThreshold => "Critical threshold".into(),
+            Setting::TemperatureUnit => "Temperature unit".into(),
+            Setting::MemoryUnit => "Memory unit".into(),
+            Setting::Panel(panel) => format!("Show {}", panel.title()),
+            Setting::Save => "Save to config file".into(),
+        }
+    }
+
+    fn value(&self, cfg: &Config) -> String {
+        match self {
+            Setting::Delay => format!("{} ms", cfg.delay),
+            Setting::MediumThreshold => format!("{}%", cfg.thresholds.medium),
+            Setting::HighThreshold => format!("{}%", cfg.thresholds.high),
+            Setting::CriticalThreshold => format!("{}%", cfg.thresholds.critical),
+            Setting::TemperatureUnit => cfg.units.temperature.symbol().into(),
+            Setting::MemoryUnit => cfg.units.memory.symbol().into(),
+            Setting::Panel(panel) => match cfg.panels.is_visible(*panel) {
+                true => "yes".into(),
+                false => "no".into(),
+            },
+            Setting::Save => String::new(),
+        }
+    }
+
+    /// Nudge the setting up or down, the thresholds are kept in order.
+    fn adjust(&self, cfg: &mut Config, up: bool) {
+        let step = |v: f64, amount: f64, min: f64, max: f64| {
+            let v = if up { v + amount } else { v - amount };
+            v.clamp(min, max)
+        };
+        let t = &mut cfg.thresholds;
+
+        match self {
+            Setting::Delay => {
+                cfg.delay = match up {
+                    true => cfg.delay.saturating_add(50),
+                    false => cfg.delay.saturating_sub(50).max(Config::MIN_DELAY),
+                }
+            }
+            Setting::MediumThreshold => t.medium = step(t.medium, 5.0, 0.0, t.high),
+            Setting::HighThreshold => t.high = step(t.high, 5.0, t.medium, t.critical),
+            Setting::CriticalThreshold => t.critical = step(t.critical, 5.0, t.high, 100.0),
+            Setting::TemperatureUnit => cfg.units.temperature = cfg.units.temperature.next(),
+            Setting::MemoryUnit => cfg.units.memory = cfg.units.memory.next(),
+            Setting::Panel(panel) => {
+                let visible = cfg.panels.visible_mut(*panel);
+                *visible = !*visible;
+            }
+            Setting::Save => {}
+        }
+    }
+
+    /// Make the same change to the running config and to `file`, which only differ by command
+    /// line flags, so a nudge to a `--delay` is a nudge to the file's delay rather than a copy of it.
+    fn apply(&self, cfg: &mut Config, file: &mut Config, up: bool) {
+        self.adjust(cfg, up);
+        self.adjust(file, up);
+    }
+}
+
+/// The in-TUI settings overlay, changes are applied to the [`Config`] immediately. What's saved is
+/// the config as it was loaded from the file plus those changes, so one-off command line flags
+/// don't end up in it.
+#[derive(Debug, Default)]
+pub struct SettingsMenu {
+    pub open: bool,
+    pub selected: usize,
+    /// Result of the last save, shown at the bottom of the overlay.
+    pub status: Option<String>,
+}
+
+impl SettingsMenu {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.status = None;
+    }
+
+    /// Move the selection down (or up) a row.
+    pub fn scroll(&mut self, down: bool) {
+        self.selected = match down {
+            true => (self.selected + 1).min(Setting::all().len() - 1),
+            false => self.selected.saturating_sub(1),
+        };
+    }
+
+    /// Handle a key press while the overlay is open, `file` is the config as loaded (before any
+    /// command line flags) and `save_to` is where `Save` writes it.
+    pub fn handle_key(
+        &mut self,
+        key: KeyEvent,
+        cfg: &mut Config,
+        file: &mut Config,
+        save_to: Option<&Path>,
+    ) {
+        let items = Setting::all();
+
+        if cfg.keys.action_for(&key) == Some(Action::Settings) {
+            self.toggle();
+            return;
+        }
+
+        match key.code {
+            KeyCode::Esc => self.toggle(),
+            // Fixed rather than from the keymap, so whatever's bound to `h`/`j`/`k`/`l` there doesn't
+            // mean something else in here.
+            KeyCode::Up => self.scroll(false),
+            KeyCode::Down => self.scroll(true),
+            KeyCode::Left | KeyCode::Char('-') => items[self.selected].apply(cfg, file, false),
+            KeyCode::Right | KeyCode::Char('+') => items[self.selected].apply(cfg, file, true),
+            KeyCode::Enter | KeyCode::Char(' ') => match items[self.selected] {
+                Setting::Save => {
+                    self.status = Some(match save_to {
+                        Some(path) => match file.save(path) {
+                            Ok(()) => format!("Saved to {}", path.display()),
+                            Err(e) => format!("Unable to save: {e}"),
+                        },
+                        None => "Nowhere to save to, try --config".into(),
+                    })
+                }
+                item => item.apply(cfg, file, true),
+            },
+            _ => {}
+        }
+    }
+
+    pub fn draw(&self, f: &mut Frame, area: Rect, cfg: &Config, theme: &Theme) {
+        let items = Setting::all();
+        let popup = centered(area, 50, items.len() as u16 + 2);
+
+        let rows = items
+            .iter()
+            .map(|item| Row::new(vec![item.label(), item.value(cfg)]))
+            .collect::<Vec<_>>();
+
+        let block = Block::default()
+            .title("Settings")
+            .title_alignment(Alignment::Center)
+            .title(
+                block::Title::from(
+                    self.status
+                        .as_deref()
+                        .unwrap_or("↑/↓ select, ←/→ or -/+ change, enter toggle"),
+                )
+                .position(block::Position::Bottom)
+                .alignment(Alignment::Center),
+            )
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(theme.border_style());
+
+        let table = Table::new(rows)
+            .block(block)
+            .widths(&[Constraint::Percentage(65), Constraint::Percentage(35)])
+            .highlight_style(theme.highlight_style());
+
+        let mut state = TableState::default();
+        state.select(Some(self.selected));
+
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(table, popup, &mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+
+    #[test]
+    fn command_line_flags_are_not_saved() {
+        let file = Config::default();
+        let mut saved = file.clone();
+        // As if run with `--no-mouse --delay 500`.
+        let mut cfg = Config {
+            mouse: false,
+            delay: 500,
+            ..file.clone()
+        };
+
+        let path = std::env::temp_dir()
+            .join(format!("nvtop-settings-{}", std::process::id()))
+            .join("config.toml");
+        let mut menu = SettingsMenu::default();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        // Refresh interval is the first row.
+        menu.handle_key(key(KeyCode::Right), &mut cfg, &mut saved, Some(&path));
+        menu.selected = Setting::all().len() - 1;
+        menu.handle_key(key(KeyCode::Enter), &mut cfg, &mut saved, Some(&path));
+
+        let loaded = Config::load(Some(&path));
+        _ = std::fs::remove_dir_all(path.parent().unwrap());
+        let loaded = loaded.unwrap();
+
+        assert_eq!(cfg.delay, 550);
+        assert_eq!(
+            loaded.delay,
+            file.delay + 50,
+            "the change made in the menu is saved, but not --delay"
+        );
+        assert!(saved.mouse);
+        assert!(loaded.mouse, "nor --no-mouse");
+        assert_eq!(loaded, saved);
+    }
+}
@@ -0,0 +1,478 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use nvml_wrapper::Nvml;
+
+use crate::{
+    config::Config,
+    errors::NvTopError,
+    gpu::{try_init_gpus, GpuInfo},
+    health::{sample_devices, Health},
+    mig::{sample_instances, MigInstance},
+    nvtop_args::StatusArgs,
+    sample::Sample,
+    terminal::Signals,
+    termite::LoggingHandle,
+};
+
+/// Everything that can go in a status template, per device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Index,
+    Uuid,
+    PciBusId,
+    Name,
+    /// Core utilisation in %
+    Utilisation,
+    /// GPU die temperature in the configured unit
+    Temperature,
+    TemperatureUnit,
+    /// In the configured unit
+    MemoryUsed,
+    MemoryFree,
+    MemoryTotal,
+    /// Set aside by the driver
+    MemoryReserved,
+    /// Memory used as a % of total
+    MemoryPercent,
+    MemoryUnit,
+    /// Memory controller utilisation in %
+    MemoryBandwidth,
+    /// In the configured unit
+    Bar1Used,
+    Bar1Total,
+    /// Average fan speed in %
+    Fan,
+    /// MHz
+    CoreClock,
+    MaxCoreClock,
+    DriverVersion,
+    NvmlVersion,
+    /// i.e `12.2`
+    CudaVersion,
+}
+
+impl Field {
+    pub const ALL: [Field; 22] = [
+        Field::Index,
+        Field::Uuid,
+        Field::PciBusId,
+        Field::Name,
+        Field::Utilisation,
+        Field::Temperature,
+        Field::TemperatureUnit,
+        Field::MemoryUsed,
+        Field::MemoryFree,
+        Field::MemoryTotal,
+        Field::MemoryReserved,
+        Field::MemoryPercent,
+        Field::MemoryUnit,
+        Field::MemoryBandwidth,
+        Field::Bar1Used,
+        Field::Bar1Total,
+        Field::Fan,
+        Field::CoreClock,
+        Field::MaxCoreClock,
+        Field::DriverVersion,
+        Field::NvmlVersion,
+        Field::CudaVersion,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Index => "index",
+            Field::Uuid => "uuid",
+            Field::PciBusId => "pci_bus_id",
+            Field::Name => "name",
+            Field::Utilisation => "utilisation",
+            Field::Temperature => "temperature",
+            Field::TemperatureUnit => "temperature_unit",
+            Field::MemoryUsed => "memory_used",
+            Field::MemoryFree => "memory_free",
+            Field::MemoryTotal => "memory_total",
+            Field::MemoryReserved => "memory_reserved",
+            Field::MemoryPercent => "memory_percent",
+            Field::MemoryUnit => "memory_unit",
+            Field::MemoryBandwidth => "memory_bandwidth",
+            Field::Bar1Used => "bar1_used",
+            Field::Bar1Total => "bar1_total",
+            Field::Fan => "fan",
+            Field::CoreClock => "core_clock",
+            Field::MaxCoreClock => "max_core_clock",
+            Field::DriverVersion => "driver_version",
+            Field::NvmlVersion => "nvml_version",
+            Field::CudaVersion => "cuda_version",
+        }
+    }
+
+    fn value(&self, gpu: &GpuInfo, sample: &Sample, cfg: &Config) -> Value {
+        if let Some(value) = self.memory_value(sample, cfg) {
+            return value;
+        }
+
+        match self {
+            Field::Index => Value::Number(f64::from(gpu.index)),
+            Field::Uuid => Value::Text(gpu.uuid.clone()),
+            Field::PciBusId => Value::Text(gpu.pci_bus_id.clone()),
+            Field::Name => Value::Text(gpu.name().unwrap_or_else(|_| gpu.card_type.clone())),
+            Field::Utilisation => sample.utilisation.map(f64::from).into(),
+            Field::Temperature => sample
+                .temperature
+                .map(|t| cfg.units.temperature.convert(t))
+                .into(),
+            Field::TemperatureUnit => Value::Text(cfg.units.temperature.symbol().into()),
+            Field::Fan => sample.fan_speed.into(),
+            Field::CoreClock => sample.core_clock.map(f64::from).into(),
+            Field::MaxCoreClock => gpu.max_core_clock.map(f64::from).into(),
+            Field::DriverVersion => Value::Text(gpu.driver.driver_version.clone()),
+            Field::NvmlVersion => Value::Text(gpu.driver.nvml_version.clone()),
+            Field::CudaVersion => Value::Text(gpu.driver.cuda_version.to_string()),
+            _ => Value::Missing,
+        }
+    }
+
+    /// The memory fields, which only need the sample, `None` for anything else.
+    fn memory_value(&self, sample: &Sample, cfg: &Config) -> Option<Value> {
+        let memory = |bytes: Option<u64>| bytes.map(|b| cfg.units.memory.convert(b)).into();
+
+        Some(match self {
+            Field::MemoryUsed => memory(sample.memory_used),
+            // Not total - used, which would count what's reserved as free.
+            Field::MemoryFree => memory(sample.memory_free),
+            Field::MemoryTotal => memory(sample.memory_total),
+            Field::MemoryReserved => memory(sample.memory_reserved),
+            Field::MemoryPercent => sample.metric(crate::alerts::Metric::Memory).into(),
+            Field::MemoryUnit => Value::Text(cfg.units.memory.symbol().into()),
+            Field::MemoryBandwidth => sample.memory_bandwidth.map(f64::from).into(),
+            Field::Bar1Used => memory(sample.bar1_used),
+            Field::Bar1Total => memory(sample.bar1_total),
+            _ => return None,
+        })
+    }
+
+    /// As for [`Field::value`], but for a MIG instance of `gpu`, which gets its own index, UUID
+    /// and name and doesn't have clocks of its own.
+    fn instance_value(
+        &self,
+        gpu: &GpuInfo,
+        instance: &MigInstance,
+        sample: &Sample,
+        cfg: &Config,
+    ) -> Value {
+        match self {
+            Field::Index => Value::Text(instance.label(gpu.index)),
+            Field::Uuid => Value::Text(instance.uuid.clone()),
+            Field::Name => Value::Text(instance.name.clone()),
+            Field::MaxCoreClock => Value::Missing,
+            _ => self.value(gpu, sample, cfg),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Field::ALL
+            .into_iter()
+            .find(|field| field.name() == s)
+            .ok_or_else(|| {
+                let names = Field::ALL.map(|f| f.name()).join(", ");
+                format!("unknown placeholder '{{{s}}}', expected one of: {names}")
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+    /// The device couldn't tell us.
+    Missing,
+}
+
+impl From<Option<f64>> for Value {
+    fn from(value: Option<f64>) -> Self {
+        value.map_or(Value::Missing, Value::Number)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Field {
+        field: Field,
+        /// Right aligned unless `<` was given.
+        left: bool,
+        width: usize,
+        /// Numbers default to none, i.e `71` not `71.0`.
+        precision: usize,
+    },
+}
+
+/// A line of text with `{placeholders}` filled in per device, i.e `GPU{index} {utilisation}%` or
+/// `{memory_used:.1}/{memory_total:.0}{memory_unit}`. Placeholders take an optional width and
+/// precision like Rust's `format!`: `{utilisation:>3}`, `{temperature:.1}`, use `{{` and `}}` for
+/// literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub const DEFAULT: &'static str = "GPU{index} {utilisation}% {temperature}{temperature_unit} {memory_used:.1}/{memory_total:.0}{memory_unit}";
+
+    pub fn render<F>(&self, mut value: F) -> String
+    where
+        F: FnMut(Field) -> Value,
+    {
+        let mut out = String::new();
+        for part in &self.parts {
+            let (field, left, width, precision) = match part {
+                Part::Literal(text) => {
+                    out.push_str(text);
+                    continue;
+                }
+                Part::Field {
+                    field,
+                    left,
+                    width,
+                    precision,
+                } => (*field, *left, *width, *precision),
+            };
+
+            let text = match value(field) {
+                Value::Number(n) => format!("{n:.precision$}"),
+                Value::Text(text) => text,
+                Value::Missing => "N/A".into(),
+            };
+            _ = match left {
+                true => write!(out, "{text:<width$}"),
+                false => write!(out, "{text:>width$}"),
+            };
+        }
+
+        out
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(format!("unclosed '{{{placeholder}'")),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(&placeholder)?);
+                }
+                '}' => return Err("unmatched '}', use '}}' for a literal brace".into()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+/// `name`, `name:.1`, `name:>3` or `name:<8.2`
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+    let field = name.trim().parse()?;
+
+    let bad_spec = || format!("can't make sense of '{{{placeholder}}}'");
+    let (left, spec) = match spec.strip_prefix('<') {
+        Some(rest) => (true, rest),
+        None => (false, spec.strip_prefix('>').unwrap_or(spec)),
+    };
+    let (width, precision) = spec.split_once('.').unwrap_or((spec, ""));
+    let number = |s: &str| match s {
+        "" => Ok(0),
+        s => s.parse::<usize>().map_err(|_| bad_spec()),
+    };
+
+    Ok(Part::Field {
+        field,
+        left,
+        width: number(width)?,
+        precision: number(precision)?,
+    })
+}
+
+/// `nvtop status`: print the template for each device once, or keep doing so every `--interval`
+/// (to `--output` if given, replacing the file each time).
+pub fn run(
+    nvml: Nvml,
+    cfg: &Config,
+    args: &StatusArgs,
+    lh: &LoggingHandle,
+) -> Result<(), NvTopError> {
+    let template = args.format.as_deref().unwrap_or(Template::DEFAULT);
+    let template = template
+        .parse::<Template>()
+        .map_err(|e| NvTopError::Config(format!("Invalid --format: {e}")))?;
+    let mut gpu_list = try_init_gpus(&nvml, &cfg.device_filters(), lh)?;
+    let mut health = vec![Health::default(); gpu_list.len()];
+    let signals = Signals::register()?;
+
+    loop {
+        let started = Instant::now();
+        // Lost devices still get a line, with N/A for anything that needs a sample.
+        let samples = sample_devices(&nvml, &mut gpu_list, &mut health, lh);
+        let instance_samples = sample_instances(&gpu_list, &health);
+        let missing = Sample::missing();
+        let line = gpu_list
+            .iter()
+            .zip(samples)
+            .flat_map(|(gpu, sample)| {
+                let device = template.render(|field| field.value(gpu, &sample, cfg));
+                // Followed by its MIG instances, if it has any.
+                let instances = gpu.mig.iter().map(|instance| {
+                    let sample = instance_samples.get(&instance.uuid).unwrap_or(&missing);
+                    template.render(|field| field.instance_value(gpu, instance, sample, cfg))
+                });
+                [device].into_iter().chain(instances).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+            .join(&args.separator);
+
+        match &args.output {
+            Some(path) => write_atomically(path, &line)?,
+            None => {
+                let mut stdout = io::stdout().lock();
+                writeln!(stdout, "{line}")?;
+                stdout.flush()?;
+            }
+        }
+
+        let Some(interval) = args.interval.map(Duration::from_millis) else {
+            break;
+        };
+        while started.elapsed() < interval && !signals.quit_requested() {
+            thread::sleep(Duration::from_millis(50).min(interval));
+        }
+        if signals.quit_requested() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// So whatever's reading the file never sees it half written.
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, format!("{contents}\n"))?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        template
+            .parse::<Template>()
+            .unwrap()
+            .render(|field| match field {
+                Field::Index => Value::Number(0.0),
+                Field::Name => Value::Text("NVIDIA GeForce RTX 3090".into()),
+                Field::Utilisation => Value::Number(87.0),
+                Field::Temperature => Value::Number(71.0),
+                Field::TemperatureUnit => Value::Text("°C".into()),
+                Field::MemoryUsed => Value::Number(21.34),
+                Field::MemoryTotal => Value::Number(24.0),
+                Field::MemoryUnit => Value::Text("G".into()),
+                _ => Value::Missing,
+            })
+    }
+
+    #[test]
+    fn render_templates() {
+        assert_eq!(
+            render("GPU{index} {utilisation}% {temperature}{temperature_unit} {memory_used:.1}/{memory_total}{memory_unit}"),
+            "GPU0 87% 71°C 21.3/24G"
+        );
+        assert_eq!(render("[{utilisation:>4}|{index:<3}]"), "[  87|0  ]");
+        assert_eq!(render("{{{fan}}}"), "{N/A}");
+        assert_eq!(render("{name}"), "NVIDIA GeForce RTX 3090");
+    }
+
+    #[test]
+    fn memory_fields_come_from_the_sample() {
+        let gib = 1_073_741_824;
+        let sample = Sample {
+            memory_used: Some(6 * gib),
+            memory_total: Some(24 * gib),
+            memory_reserved: Some(gib / 2),
+            memory_free: Some(17 * gib + gib / 2),
+            memory_bandwidth: Some(34),
+            bar1_used: Some(gib / 4),
+            bar1_total: Some(256 * gib),
+            ..Sample::missing()
+        };
+        let cfg = Config::default();
+        let value = |field: Field| field.memory_value(&sample, &cfg).unwrap();
+
+        assert_eq!(
+            value(Field::MemoryFree),
+            Value::Number(17.5),
+            "reserved isn't free"
+        );
+        assert_eq!(value(Field::MemoryReserved), Value::Number(0.5));
+        assert_eq!(value(Field::MemoryBandwidth), Value::Number(34.0));
+        assert_eq!(value(Field::Bar1Used), Value::Number(0.25));
+        assert_eq!(value(Field::Bar1Total), Value::Number(256.0));
+        assert_eq!(Field::Fan.memory_value(&sample, &cfg), None);
+        assert_eq!(
+            Field::Bar1Used.memory_value(&Sample::missing(), &cfg),
+            Some(Value::Missing)
+        );
+    }
+
+    #[test]
+    fn bad_templates() {
+        let err = "{utilization}".parse::<Template>().unwrap_err();
+        assert!(err.contains("memory_percent"), "{err}");
+        assert!("{fan:x}".parse::<Template>().is_err());
+        assert!("50}".parse::<Template>().is_err());
+        assert!("{fan".parse::<Template>().is_err());
+        // Every field has a name that parses back.
+        for field in Field::ALL {
+            assert_eq!(field.name().parse::<Field>(), Ok(field));
+        }
+    }
+}
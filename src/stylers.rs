@@ -1,4 +1,7 @@
 use ratatui::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::theme::Theme;
 
 // Define an enum for severity levels
 pub enum Severity {
@@ -9,44 +12,122 @@ pub enum Severity {
 }
 
 impl Severity {
-    const COLORS: [(u8, u8, u8); 4] = [
-        (66, 84, 245),  // Blueish
-        (41, 211, 152), // Greenish
-        (217, 148, 30), // Orangeish
-        (212, 22, 8),   // Reddish
-    ];
-
-    pub fn style_for(&self) -> Style {
-        // Create styles for each severity level
-        let styles = Self::COLORS
-            .iter()
-            .map(|(r, g, b)| {
-                Style::default()
-                    .fg(Color::Rgb(*r, *g, *b))
-                    .add_modifier(Modifier::BOLD | Modifier::ITALIC)
-            })
-            .collect::<Vec<Style>>();
-
-        *match self {
-            Severity::Low => &styles[0],      // Pink
-            Severity::Medium => &styles[1],   // Green
-            Severity::High => &styles[2],     // Orange
-            Severity::Critical => &styles[3], //Red
+    pub fn style_for(&self, theme: &Theme) -> Style {
+        theme.severity_style(self)
+    }
+}
+
+/// The RGB colour used for each [`Severity`], i.e `critical = [212, 22, 8]` in the config file, overriding the theme's.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeverityColors {
+    pub low: (u8, u8, u8),
+    pub medium: (u8, u8, u8),
+    pub high: (u8, u8, u8),
+    pub critical: (u8, u8, u8),
+}
+
+impl Default for SeverityColors {
+    fn default() -> Self {
+        Self {
+            low: (66, 84, 245),     // Blueish
+            medium: (41, 211, 152), // Greenish
+            high: (217, 148, 30),   // Orangeish
+            critical: (212, 22, 8), // Reddish
+        }
+    }
+}
+
+/// The percentages at which each [`Severity`] kicks in, anything under `medium` is [`Severity::Low`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawThresholds")]
+pub struct SeverityThresholds {
+    pub medium: f64,
+    pub high: f64,
+    pub critical: f64,
+}
+
+impl Default for SeverityThresholds {
+    fn default() -> Self {
+        Self {
+            medium: 40.0,
+            high: 70.0,
+            critical: 80.0,
+        }
+    }
+}
+
+impl SeverityThresholds {
+    /// Work out which [`Severity`] the ratio `n` (0..1) falls in.
+    pub fn severity_of<N>(&self, n: N) -> Severity
+    where
+        N: Into<f64>,
+    {
+        let percent = n.into().clamp(0.0, 1.0) * 100.0;
+
+        match percent {
+            _ if percent < self.medium => Severity::Low,
+            _ if percent < self.high => Severity::Medium,
+            _ if percent < self.critical => Severity::High,
+            _ => Severity::Critical,
         }
     }
 }
 
-/// Work out the %ile that `n` is in out of 0..100
+/// What's actually in the file, checked for sanity before becoming [`SeverityThresholds`].
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawThresholds {
+    medium: f64,
+    high: f64,
+    critical: f64,
+}
+
+impl Default for RawThresholds {
+    fn default() -> Self {
+        let SeverityThresholds {
+            medium,
+            high,
+            critical,
+        } = SeverityThresholds::default();
+        Self {
+            medium,
+            high,
+            critical,
+        }
+    }
+}
+
+impl TryFrom<RawThresholds> for SeverityThresholds {
+    type Error = String;
+
+    fn try_from(raw: RawThresholds) -> Result<Self, Self::Error> {
+        if !(0.0..=100.0).contains(&raw.medium)
+            || !(0.0..=100.0).contains(&raw.high)
+            || !(0.0..=100.0).contains(&raw.critical)
+        {
+            return Err("thresholds are percentages and must be within 0..=100".into());
+        }
+
+        if raw.medium > raw.high || raw.high > raw.critical {
+            return Err(format!(
+                "thresholds must satisfy medium <= high <= critical, got {} / {} / {}",
+                raw.medium, raw.high, raw.critical
+            ));
+        }
+
+        Ok(Self {
+            medium: raw.medium,
+            high: raw.high,
+            critical: raw.critical,
+        })
+    }
+}
+
+/// Work out the %ile that `n` is in out of 0..100, using the default thresholds.
 pub fn calculate_severity<N>(n: N) -> Severity
 where
     N: Into<f64>,
 {
-    let clamped_n = n.into().clamp(0.0, 1.0);
-
-    match clamped_n {
-        _ if clamped_n < 0.40 => Severity::Low,
-        _ if clamped_n < 0.70 => Severity::Medium,
-        _ if clamped_n < 0.80 => Severity::High,
-        _ => Severity::Critical,
-    }
+    SeverityThresholds::default().severity_of(n)
 }
@@ -0,0 +1,145 @@
+use std::io;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Once,
+};
+
+use crossterm::{cursor, event, execute, terminal};
+
+/// Puts the terminal into raw mode on the alternate screen, and puts it back however we leave:
+/// returning, bailing out with `?` or panicking.
+pub struct TerminalGuard {
+    mouse: bool,
+}
+
+impl TerminalGuard {
+    pub fn enter(mouse: bool) -> io::Result<Self> {
+        install_panic_hook();
+
+        let guard = Self { mouse };
+        guard.setup()?;
+
+        Ok(guard)
+    }
+
+    fn setup(&self) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stderr(), terminal::EnterAlternateScreen)?;
+        if self.mouse {
+            execute!(io::stderr(), event::EnableMouseCapture)?;
+        }
+
+        Ok(())
+    }
+
+    /// Hand the terminal back to the shell and stop, picking up where we left off once continued.
+    #[cfg(unix)]
+    pub fn suspend(&self) -> io::Result<()> {
+        restore();
+        signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP)?;
+        self.setup()
+    }
+
+    /// Put our terminal modes back after being stopped and continued behind our back, i.e `kill -STOP`.
+    pub fn resume(&self) -> io::Result<()> {
+        self.setup()
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leave the alternate screen and raw mode, errors are ignored as there's nothing left to do about them.
+pub fn restore() {
+    _ = execute!(
+        io::stderr(),
+        event::DisableMouseCapture,
+        terminal::LeaveAlternateScreen,
+        cursor::Show
+    );
+    _ = terminal::disable_raw_mode();
+}
+
+/// Restore the terminal before the default hook prints the panic, otherwise it's lost with the alternate screen.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            hook(info);
+        }));
+    });
+}
+
+/// The signals we act on, checked once per frame.
+pub struct Signals {
+    quit: Arc<AtomicBool>,
+    suspend: Arc<AtomicBool>,
+    resumed: Arc<AtomicBool>,
+}
+
+impl Signals {
+    /// SIGINT, SIGTERM and SIGHUP ask us to quit, SIGTSTP to suspend and SIGCONT tells us we've been continued.
+    pub fn register() -> io::Result<Self> {
+        use signal_hook::{consts::*, flag};
+
+        let signals = Self {
+            quit: Arc::default(),
+            suspend: Arc::default(),
+            resumed: Arc::default(),
+        };
+
+        flag::register(SIGINT, Arc::clone(&signals.quit))?;
+        flag::register(SIGTERM, Arc::clone(&signals.quit))?;
+        #[cfg(unix)]
+        {
+            flag::register(SIGHUP, Arc::clone(&signals.quit))?;
+            flag::register(SIGTSTP, Arc::clone(&signals.suspend))?;
+            flag::register(SIGCONT, Arc::clone(&signals.resumed))?;
+        }
+
+        Ok(signals)
+    }
+
+    pub fn quit_requested(&self) -> bool {
+        self.quit.load(Ordering::Relaxed)
+    }
+
+    /// Whether we've been asked to suspend since last checked.
+    pub fn take_suspend(&self) -> bool {
+        self.suspend.swap(false, Ordering::Relaxed)
+    }
+
+    /// Whether we've been continued since last checked.
+    pub fn take_resumed(&self) -> bool {
+        self.resumed.swap(false, Ordering::Relaxed)
+    }
+
+    /// Suspend as if Ctrl-Z had been pressed outside of raw mode.
+    pub fn raise_suspend(&self) {
+        #[cfg(unix)]
+        let _ = signal_hook::low_level::raise(signal_hook::consts::SIGTSTP);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signals_are_flagged() {
+        let signals = Signals::register().unwrap();
+
+        signal_hook::low_level::raise(signal_hook::consts::SIGTSTP).unwrap();
+        assert!(signals.take_suspend());
+        assert!(!signals.take_suspend());
+
+        signal_hook::low_level::raise(signal_hook::consts::SIGHUP).unwrap();
+        assert!(signals.quit_requested());
+    }
+}
@@ -20,6 +20,7 @@ pub enum LogType {
 }
 
 /// An abstraction to allow logging to a file, as opposed to stdout, which is hard when developing a TUI app.
+#[derive(Clone)]
 pub struct LoggingHandle {
     /// The channel you send LogType(Some string) across
     pub sender: Sender<LogType>,
@@ -0,0 +1,411 @@
+use ratatui::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    stylers::{Severity, SeverityColors},
+};
+
+/// The built-in palettes, pick one with `--theme` or `theme = "..."` in the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    Solarized,
+    HighContrast,
+    /// Blues and oranges from the Okabe-Ito palette, distinguishable with the common forms of colour blindness.
+    ColourBlind,
+    /// No colour at all, severity is shown with text modifiers instead.
+    Monochrome,
+}
+
+/// How many colours the terminal can show, we downgrade RGB values to fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    /// `NO_COLOR` is set, or the terminal is dumb.
+    NoColor,
+}
+
+impl ColorDepth {
+    /// Work out what the current terminal supports from the environment.
+    pub fn detect() -> Self {
+        let var = |name| std::env::var(name).ok();
+        Self::from_env(
+            var("NO_COLOR").as_deref(),
+            var("COLORTERM").as_deref(),
+            var("TERM").as_deref(),
+        )
+    }
+
+    /// See <https://no-color.org> and <https://github.com/termstandard/colors>.
+    pub fn from_env(no_color: Option<&str>, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if no_color.is_some_and(|v| !v.is_empty()) || term == Some("dumb") {
+            return ColorDepth::NoColor;
+        }
+
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return ColorDepth::TrueColor;
+        }
+
+        match term {
+            Some(term) if term.contains("256color") => ColorDepth::Ansi256,
+            // Windows Terminal and modern conhost don't set TERM but do support RGB.
+            None if cfg!(target_os = "windows") => ColorDepth::TrueColor,
+            _ => ColorDepth::Ansi16,
+        }
+    }
+
+    /// Squash `color` into something this depth can show.
+    pub fn adapt(&self, color: Color) -> Color {
+        match (self, color) {
+            (ColorDepth::NoColor, _) => Color::Reset,
+            (ColorDepth::TrueColor, c) => c,
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(rgb_to_ansi256(r, g, b)),
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => rgb_to_ansi16(r, g, b),
+            (ColorDepth::Ansi16, Color::Indexed(i)) if i >= 16 => {
+                let (r, g, b) = ansi256_to_rgb(i);
+                rgb_to_ansi16(r, g, b)
+            }
+            (_, c) => c,
+        }
+    }
+}
+
+/// Every colour the UI uses, already adapted to the terminal's [`ColorDepth`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Borders, the tab bar and the core utilisation gauge.
+    pub border: Color,
+    /// Panel titles that want to stand out, i.e `Misc`.
+    pub accent: Color,
+    /// Text drawn over the gauges.
+    pub label_fg: Color,
+    pub label_bg: Color,
+    /// The selected row in lists and tables.
+    pub highlight_fg: Color,
+    pub highlight_bg: Color,
+    /// Low, medium, high and critical.
+    pub severity: [Color; 4],
+    pub severity_modifiers: [Modifier; 4],
+}
+
+impl Theme {
+    pub fn named(name: ThemeName, depth: ColorDepth) -> Self {
+        let bold_italic = Modifier::BOLD | Modifier::ITALIC;
+        let rgb = |(r, g, b): (u8, u8, u8)| Color::Rgb(r, g, b);
+        let defaults = SeverityColors::default();
+
+        let theme = match name {
+            ThemeName::Default => Theme {
+                border: Color::Green,
+                accent: Color::Magenta,
+                label_fg: Color::White,
+                label_bg: Color::Black,
+                highlight_fg: Color::Black,
+                highlight_bg: Color::Green,
+                severity: [
+                    defaults.low,
+                    defaults.medium,
+                    defaults.high,
+                    defaults.critical,
+                ]
+                .map(rgb),
+                severity_modifiers: [bold_italic; 4],
+            },
+            ThemeName::Solarized => Theme {
+                border: rgb((38, 139, 210)),
+                accent: rgb((211, 54, 130)),
+                label_fg: rgb((238, 232, 213)),
+                label_bg: rgb((7, 54, 66)),
+                highlight_fg: rgb((0, 43, 54)),
+                highlight_bg: rgb((38, 139, 210)),
+                severity: [(42, 161, 152), (133, 153, 0), (181, 137, 0), (220, 50, 47)].map(rgb),
+                severity_modifiers: [bold_italic; 4],
+            },
+            ThemeName::HighContrast => Theme {
+                border: Color::White,
+                accent: Color::LightYellow,
+                label_fg: Color::White,
+                label_bg: Color::Black,
+                highlight_fg: Color::Black,
+                highlight_bg: Color::White,
+                severity: [
+                    Color::LightCyan,
+                    Color::LightGreen,
+                    Color::LightYellow,
+                    Color::LightRed,
+                ],
+                severity_modifiers: [Modifier::BOLD; 4],
+            },
+            ThemeName::ColourBlind => Theme {
+                border: rgb((86, 180, 233)),
+                accent: rgb((204, 121, 167)),
+                label_fg: Color::White,
+                label_bg: Color::Black,
+                highlight_fg: Color::Black,
+                highlight_bg: rgb((86, 180, 233)),
+                severity: [(86, 180, 233), (0, 114, 178), (230, 159, 0), (213, 94, 0)].map(rgb),
+                severity_modifiers: [bold_italic; 4],
+            },
+            ThemeName::Monochrome => Theme {
+                border: Color::Reset,
+                accent: Color::Reset,
+                label_fg: Color::Reset,
+                label_bg: Color::Reset,
+                highlight_fg: Color::Reset,
+                highlight_bg: Color::Reset,
+                severity: [Color::Reset; 4],
+                severity_modifiers: [
+                    Modifier::DIM,
+                    Modifier::empty(),
+                    Modifier::BOLD,
+                    Modifier::BOLD | Modifier::UNDERLINED,
+                ],
+            },
+        };
+
+        theme.adapted_to(depth)
+    }
+
+    /// The theme for `name`, or monochrome if the terminal can't do colour.
+    pub fn detect(name: ThemeName) -> Self {
+        match ColorDepth::detect() {
+            ColorDepth::NoColor => Self::named(ThemeName::Monochrome, ColorDepth::NoColor),
+            depth => Self::named(name, depth),
+        }
+    }
+
+    /// The configured theme, with the config's `[colors]` (if any) swapped in.
+    pub fn from_config(cfg: &Config) -> Self {
+        let theme = Self::detect(cfg.theme);
+        match (cfg.colors, ColorDepth::detect()) {
+            (_, ColorDepth::NoColor) | (None, _) => theme,
+            (Some(colors), depth) => theme.with_severity_colors(colors, depth),
+        }
+    }
+
+    /// Swap in user supplied severity colours.
+    pub fn with_severity_colors(mut self, colors: SeverityColors, depth: ColorDepth) -> Self {
+        self.severity = [colors.low, colors.medium, colors.high, colors.critical]
+            .map(|(r, g, b)| depth.adapt(Color::Rgb(r, g, b)));
+        self
+    }
+
+    fn adapted_to(mut self, depth: ColorDepth) -> Self {
+        for c in [
+            &mut self.border,
+            &mut self.accent,
+            &mut self.label_fg,
+            &mut self.label_bg,
+            &mut self.highlight_fg,
+            &mut self.highlight_bg,
+        ]
+        .into_iter()
+        .chain(self.severity.iter_mut())
+        {
+            *c = depth.adapt(*c);
+        }
+        self
+    }
+
+    pub fn border_style(&self) -> Style {
+        Style::default().fg(self.border)
+    }
+
+    pub fn accent_style(&self) -> Style {
+        Style::default()
+            .fg(self.accent)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    pub fn label_style(&self) -> Style {
+        Style::default()
+            .fg(self.label_fg)
+            .bg(self.label_bg)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    pub fn highlight_style(&self) -> Style {
+        // Reversed keeps the selection visible when there are no colours to play with.
+        match self.highlight_bg {
+            Color::Reset => Style::default().add_modifier(Modifier::REVERSED),
+            bg => Style::default().fg(self.highlight_fg).bg(bg),
+        }
+    }
+
+    /// Greys out a device that's stopped answering.
+    pub fn lost_style(&self) -> Style {
+        match self.highlight_bg {
+            Color::Reset => Style::default().add_modifier(Modifier::DIM),
+            _ => Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::DIM),
+        }
+    }
+
+    pub fn severity_style(&self, severity: &Severity) -> Style {
+        let i = match severity {
+            Severity::Low => 0,
+            Severity::Medium => 1,
+            Severity::High => 2,
+            Severity::Critical => 3,
+        };
+
+        Style::default()
+            .fg(self.severity[i])
+            .add_modifier(self.severity_modifiers[i])
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::named(ThemeName::Default, ColorDepth::TrueColor)
+    }
+}
+
+/// The xterm 6x6x6 colour cube levels.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Nearest xterm-256 index, picking between the colour cube and the greyscale ramp.
+fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let nearest_level = |v: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&i| (i32::from(CUBE[i]) - i32::from(v)).abs())
+            .unwrap_or(0) as u8
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let avg = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    let grey_index = if avg < 8 {
+        16
+    } else if avg > 238 {
+        231
+    } else {
+        232 + ((avg - 8) / 10).min(23) as u8
+    };
+
+    if distance((r, g, b), ansi256_to_rgb(grey_index))
+        < distance((r, g, b), ansi256_to_rgb(cube_index))
+    {
+        grey_index
+    } else {
+        cube_index
+    }
+}
+
+fn ansi256_to_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => ANSI16[usize::from(i)].1,
+        16..=231 => {
+            let i = i - 16;
+            (
+                CUBE[usize::from(i / 36)],
+                CUBE[usize::from((i / 6) % 6)],
+                CUBE[usize::from(i % 6)],
+            )
+        }
+        _ => {
+            let v = 8 + (i - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+/// The 16 basic colours, with the RGB values xterm uses for them.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map_or(Color::Reset, |(c, _)| *c)
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_color_depth() {
+        use ColorDepth::*;
+
+        assert_eq!(
+            ColorDepth::from_env(Some("1"), Some("truecolor"), None),
+            NoColor
+        );
+        assert_eq!(
+            ColorDepth::from_env(Some(""), Some("truecolor"), None),
+            TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_env(None, Some("24bit"), Some("xterm")),
+            TrueColor
+        );
+        assert_eq!(
+            ColorDepth::from_env(None, None, Some("screen-256color")),
+            Ansi256
+        );
+        assert_eq!(ColorDepth::from_env(None, None, Some("linux")), Ansi16);
+        assert_eq!(ColorDepth::from_env(None, None, Some("dumb")), NoColor);
+    }
+
+    #[test]
+    fn downgrades_rgb() {
+        assert_eq!(rgb_to_ansi256(255, 0, 0), 196);
+        assert_eq!(rgb_to_ansi256(0, 0, 0), 16);
+        assert_eq!(rgb_to_ansi256(128, 128, 128), 244);
+        assert_eq!(rgb_to_ansi16(212, 22, 8), Color::Red);
+        assert_eq!(rgb_to_ansi16(41, 211, 152), Color::Cyan);
+
+        assert_eq!(
+            ColorDepth::Ansi16.adapt(Color::Indexed(196)),
+            Color::LightRed
+        );
+        assert_eq!(ColorDepth::Ansi16.adapt(Color::Green), Color::Green);
+        assert_eq!(ColorDepth::NoColor.adapt(Color::Green), Color::Reset);
+    }
+
+    #[test]
+    fn every_theme_fits_16_colours() {
+        for name in [
+            ThemeName::Default,
+            ThemeName::Solarized,
+            ThemeName::HighContrast,
+            ThemeName::ColourBlind,
+            ThemeName::Monochrome,
+        ] {
+            let theme = Theme::named(name, ColorDepth::Ansi16);
+            assert!(theme
+                .severity
+                .iter()
+                .all(|c| !matches!(c, Color::Rgb(..) | Color::Indexed(..))));
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use std::{ffi::c_ulong, fmt, fs, path::Path};
+
+#[cfg(target_os = "linux")]
+use nvml_wrapper::enum_wrappers::device::TopologyLevel;
+use nvml_wrapper::Nvml;
+
+use crate::{
+    config::Config,
+    devices::sysfs_bus_id,
+    errors::NvTopError,
+    gpu::{try_init_gpus, GpuInfo},
+    nvlink::{self, LinksSample},
+    termite::LoggingHandle,
+};
+
+/// How a pair of devices reach each other, the same letters as `nvidia-smi topo -m`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connection {
+    /// The device itself.
+    Same,
+    /// A bonded set of this many NVLinks.
+    NvLink(usize),
+    /// At most a single PCIe bridge.
+    Pix,
+    /// Multiple PCIe bridges, without going through a host bridge.
+    Pxb,
+    /// A PCIe host bridge, i.e the CPU.
+    Phb,
+    /// Host bridges within a NUMA node.
+    Node,
+    /// Across NUMA nodes, i.e QPI/UPI.
+    Sys,
+    /// NVML couldn't say.
+    Unknown,
+}
+
+impl Connection {
+    pub const LEGEND: [(&'static str, &'static str); 7] = [
+        ("X", "Self"),
+        ("SYS", "Connection traversing PCIe as well as the SMP interconnect between NUMA nodes (e.g., QPI/UPI)"),
+        ("NODE", "Connection traversing PCIe as well as the interconnect between PCIe Host Bridges within a NUMA node"),
+        ("PHB", "Connection traversing PCIe as well as a PCIe Host Bridge (typically the CPU)"),
+        ("PXB", "Connection traversing multiple PCIe bridges (without traversing the PCIe Host Bridge)"),
+        ("PIX", "Connection traversing at most a single PCIe bridge"),
+        ("NV#", "Connection traversing a bonded set of # NVLinks"),
+    ];
+}
+
+#[cfg(target_os = "linux")]
+impl From<TopologyLevel> for Connection {
+    fn from(level: TopologyLevel) -> Self {
+        match level {
+            TopologyLevel::Internal | TopologyLevel::Single => Connection::Pix,
+            TopologyLevel::Multiple => Connection::Pxb,
+            TopologyLevel::HostBridge => Connection::Phb,
+            TopologyLevel::Node => Connection::Node,
+            TopologyLevel::System => Connection::Sys,
+        }
+    }
+}
+
+impl fmt::Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Connection::Same => write!(f, "X"),
+            Connection::NvLink(n) => write!(f, "NV{n}"),
+            Connection::Pix => write!(f, "PIX"),
+            Connection::Pxb => write!(f, "PXB"),
+            Connection::Phb => write!(f, "PHB"),
+            Connection::Node => write!(f, "NODE"),
+            Connection::Sys => write!(f, "SYS"),
+            Connection::Unknown => write!(f, "N/A"),
+        }
+    }
+}
+
+/// How every device is connected to every other, and which CPUs and NUMA node each is closest
+/// to. Read once with [`Topology::read`], or put together by hand for testing.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Topology {
+    /// i.e `GPU0`
+    pub devices: Vec<String>,
+    /// Of the devices read, to tell when they've changed out from under us.
+    pub uuids: Vec<String>,
+    /// `connections[i][j]` is how `devices[i]` gets to `devices[j]`.
+    pub connections: Vec<Vec<Connection>>,
+    /// The CPUs best placed to feed each device, i.e `0-23,48-71`.
+    pub cpu_affinity: Vec<Option<String>>,
+    pub numa_node: Vec<Option<u32>>,
+}
+
+impl Topology {
+    /// CPU affinity masks are asked for in this many words, enough for 1024 CPUs even where
+    /// they're 32 bit.
+    const CPU_MASK_WORDS: usize = 32;
+
+    /// NVLinks win over PCIe where a pair have both, NUMA nodes come from `sysfs`, usually `/sys`.
+    pub fn read(gpu_list: &[GpuInfo], sysfs: &Path) -> Self {
+        let links = gpu_list
+            .iter()
+            .map(|gpu| LinksSample::take(gpu))
+            .collect::<Vec<_>>();
+        let bus_ids = gpu_list
+            .iter()
+            .map(|gpu| gpu.pci_bus_id.as_str())
+            .collect::<Vec<_>>();
+        let counts = nvlink::link_counts(&bus_ids, &links.iter().map(Some).collect::<Vec<_>>());
+
+        let connections = gpu_list
+            .iter()
+            .enumerate()
+            .map(|(i, gpu)| {
+                gpu_list
+                    .iter()
+                    .enumerate()
+                    .map(|(j, other)| match counts[i][j] {
+                        _ if i == j => Connection::Same,
+                        0 => pcie_connection(gpu, other),
+                        n => Connection::NvLink(n),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            devices: gpu_list
+                .iter()
+                .map(|gpu| format!("GPU{}", gpu.index))
+                .collect(),
+            uuids: gpu_list.iter().map(|gpu| gpu.uuid.clone()).collect(),
+            connections,
+            cpu_affinity: gpu_list.iter().map(cpu_affinity).collect(),
+            numa_node: bus_ids.iter().map(|id| numa_node(sysfs, id)).collect(),
+        }
+    }
+
+    /// Whether this was read from `gpu_list`, a rescan can swap devices without changing how
+    /// many there are.
+    pub fn describes(&self, gpu_list: &[GpuInfo]) -> bool {
+        self.uuids.iter().eq(gpu_list.iter().map(|gpu| &gpu.uuid))
+    }
+
+    /// Rows of cells, a header and then a row per device, each ending in its CPU and NUMA
+    /// affinity.
+    pub fn cells(&self) -> Vec<Vec<String>> {
+        let na = || "N/A".to_string();
+        let header = std::iter::once(String::new())
+            .chain(self.devices.iter().cloned())
+            .chain(["CPU Affinity".into(), "NUMA Affinity".into()])
+            .collect();
+
+        std::iter::once(header)
+            .chain(self.devices.iter().enumerate().map(|(i, device)| {
+                std::iter::once(device.clone())
+                    .chain(self.connections[i].iter().map(ToString::to_string))
+                    .chain([
+                        self.cpu_affinity[i].clone().unwrap_or_else(na),
+                        self.numa_node[i].map_or_else(na, |node| node.to_string()),
+                    ])
+                    .collect()
+            }))
+            .collect()
+    }
+
+    /// `nvtop topo`: the matrix in columns, followed by a legend, i.e:
+    /// ```text
+    ///       GPU0  GPU1  CPU Affinity  NUMA Affinity
+    /// GPU0  X     NV4   0-23,48-71    0
+    /// GPU1  NV4   X     24-47,72-95   1
+    /// ```
+    pub fn matrix(&self) -> String {
+        let cells = self.cells();
+        let columns = cells.first().map_or(0, Vec::len);
+        let widths = (0..columns)
+            .map(|c| {
+                cells
+                    .iter()
+                    .map(|row| row[c].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+
+        let mut lines = cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{cell:width$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+
+        lines.push(String::new());
+        lines.push("Legend:".into());
+        lines.extend(
+            Connection::LEGEND
+                .iter()
+                .map(|(short, long)| format!("  {short:4} = {long}")),
+        );
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pcie_connection(gpu: &GpuInfo, other: &GpuInfo) -> Connection {
+    // NVML wants the other device by value.
+    gpu.nvml()
+        .device_by_uuid(other.uuid.as_str())
+        .and_then(|other| gpu.topology_common_ancestor(other))
+        .map_or(Connection::Unknown, Connection::from)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pcie_connection(_: &GpuInfo, _: &GpuInfo) -> Connection {
+    Connection::Unknown
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_affinity(gpu: &GpuInfo) -> Option<String> {
+    let masks = gpu.cpu_affinity(Topology::CPU_MASK_WORDS).ok()?;
+    let list = cpu_list(&masks);
+    (!list.is_empty()).then_some(list)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_affinity(_: &GpuInfo) -> Option<String> {
+    None
+}
+
+/// CPU bitmasks, lowest CPUs first, as a list of ranges like `/sys` gives them, i.e `0-23,48-71`.
+pub fn cpu_list(masks: &[c_ulong]) -> String {
+    let bits = c_ulong::BITS as usize;
+    let cpus = masks
+        .iter()
+        .enumerate()
+        .flat_map(|(word, mask)| {
+            (0..bits)
+                .filter(move |bit| mask >> bit & 1 == 1)
+                .map(move |bit| word * bits + bit)
+        })
+        .collect::<Vec<_>>();
+
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for cpu in cpus {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == cpu => *end = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{start}-{end}"),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// From `<sysfs>/bus/pci/devices/<bus id>/numa_node`, `None` if there isn't one (the kernel says
+/// `-1`) or it can't be read.
+pub fn numa_node(sysfs: &Path, bus_id: &str) -> Option<u32> {
+    let path = sysfs
+        .join("bus/pci/devices")
+        .join(sysfs_bus_id(bus_id)?)
+        .join("numa_node");
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// `nvtop topo`: print the matrix for every selected device and exit.
+pub fn run(nvml: Nvml, cfg: &Config, lh: &LoggingHandle) -> Result<(), NvTopError> {
+    let gpu_list = try_init_gpus(&nvml, &cfg.device_filters(), lh)?;
+    println!("{}", Topology::read(&gpu_list, Path::new("/sys")).matrix());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_lists() {
+        assert_eq!(cpu_list(&[0xff_ffff, 0]), "0-23");
+        assert_eq!(cpu_list(&[0b1011]), "0-1,3");
+        assert_eq!(
+            cpu_list(&[0, 0b1]),
+            format!("{}", c_ulong::BITS),
+            "the second word starts after the first"
+        );
+        assert_eq!(cpu_list(&[0, 0]), "");
+    }
+
+    #[test]
+    fn numa_nodes_from_sysfs() {
+        let sysfs = std::env::temp_dir().join(format!("nvtop-topo-{}", std::process::id()));
+        let device = |id: &str, node: &str| {
+            let dir = sysfs.join("bus/pci/devices").join(id);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("numa_node"), node).unwrap();
+        };
+        device("0000:3b:00.0", "1\n");
+        device("0000:5e:00.0", "-1\n");
+
+        assert_eq!(numa_node(&sysfs, "00000000:3B:00.0"), Some(1));
+        assert_eq!(numa_node(&sysfs, "00000000:5E:00.0"), None);
+        assert_eq!(numa_node(&sysfs, "00000000:AF:00.0"), None);
+
+        fs::remove_dir_all(&sysfs).unwrap();
+    }
+
+    #[test]
+    fn fake_topology_matrix() {
+        use Connection::*;
+
+        let topology = Topology {
+            devices: vec!["GPU0".into(), "GPU1".into(), "GPU2".into()],
+            uuids: vec![],
+            connections: vec![
+                vec![Same, NvLink(4), Sys],
+                vec![NvLink(4), Same, Node],
+                vec![Sys, Node, Same],
+            ],
+            cpu_affinity: vec![Some("0-23,48-71".into()), Some("24-47".into()), None],
+            numa_node: vec![Some(0), Some(1), None],
+        };
+        let matrix = topology.matrix();
+        let lines = matrix.lines().collect::<Vec<_>>();
+
+        assert_eq!(
+            lines[0],
+            "      GPU0  GPU1  GPU2  CPU Affinity  NUMA Affinity"
+        );
+        assert_eq!(lines[1], "GPU0  X     NV4   SYS   0-23,48-71    0");
+        assert_eq!(lines[3], "GPU2  SYS   NODE  X     N/A           N/A");
+        assert_eq!(lines[5], "Legend:");
+    }
+}
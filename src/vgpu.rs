@@ -0,0 +1,190 @@
+use std::fmt;
+
+use nvml_wrapper::{error::NvmlError, Device};
+
+use crate::raw;
+
+/// Whether the device is virtualised, and which side of it we're on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtualizationMode {
+    /// Bare metal.
+    None,
+    /// Handed whole to a VM.
+    Passthrough,
+    /// A vGPU, seen from inside the VM.
+    Guest,
+    /// Hosting vGPUs for VMs.
+    Host,
+    /// Hosting a vSGA (shared graphics) device.
+    HostVsga,
+    Unknown(u32),
+}
+
+impl VirtualizationMode {
+    /// From NVML's `nvmlGpuVirtualizationMode_t`.
+    pub fn from_raw(mode: u32) -> Self {
+        match mode {
+            0 => VirtualizationMode::None,
+            1 => VirtualizationMode::Passthrough,
+            2 => VirtualizationMode::Guest,
+            3 => VirtualizationMode::Host,
+            4 => VirtualizationMode::HostVsga,
+            other => VirtualizationMode::Unknown(other),
+        }
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(
+            self,
+            VirtualizationMode::Host | VirtualizationMode::HostVsga
+        )
+    }
+}
+
+impl fmt::Display for VirtualizationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VirtualizationMode::None => write!(f, "none"),
+            VirtualizationMode::Passthrough => write!(f, "passthrough"),
+            VirtualizationMode::Guest => write!(f, "vGPU guest"),
+            VirtualizationMode::Host => write!(f, "vGPU host"),
+            VirtualizationMode::HostVsga => write!(f, "vSGA host"),
+            VirtualizationMode::Unknown(mode) => write!(f, "unknown ({mode})"),
+        }
+    }
+}
+
+/// A kind of vGPU a host can hand out, i.e `GRID A100-4C`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VgpuType {
+    pub id: u32,
+    pub name: String,
+    /// Bytes of frame buffer each vGPU of this type gets.
+    pub framebuffer: Option<u64>,
+}
+
+impl VgpuType {
+    /// Every type `device` supports, empty anywhere but a vGPU host.
+    pub fn supported(device: &Device) -> Result<Vec<Self>, NvmlError> {
+        raw::supported_vgpu_types(device)?
+            .into_iter()
+            .map(Self::read)
+            .collect()
+    }
+
+    fn read(id: u32) -> Result<Self, NvmlError> {
+        Ok(Self {
+            id,
+            name: raw::vgpu_type_name(id)?,
+            framebuffer: raw::vgpu_type_framebuffer(id).ok(),
+        })
+    }
+}
+
+/// A vGPU running on the host, as of the last time we looked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VgpuInstance {
+    pub id: u32,
+    pub uuid: Option<String>,
+    pub vm_id: Option<String>,
+    pub vgpu_type: Option<VgpuType>,
+    /// Bytes
+    pub fb_used: Option<u64>,
+    /// SM utilisation in %
+    pub utilisation: Option<f64>,
+    /// Memory controller utilisation in %
+    pub memory_utilisation: Option<f64>,
+}
+
+impl VgpuInstance {
+    /// Everything running on `device`, looking types up in `types` (the device's supported types)
+    /// first. Anything NVML won't say about an instance is left as `None`.
+    pub fn active(device: &Device, types: &[VgpuType]) -> Result<Vec<Self>, NvmlError> {
+        let utilisation = raw::vgpu_utilisation(device).unwrap_or_default();
+
+        Ok(raw::active_vgpus(device)?
+            .into_iter()
+            .map(|id| {
+                let vgpu_type = raw::vgpu_instance_type(id).ok().and_then(|type_id| {
+                    match types.iter().find(|t| t.id == type_id) {
+                        Some(known) => Some(known.clone()),
+                        None => VgpuType::read(type_id).ok(),
+                    }
+                });
+                let utilisation = utilisation.iter().find(|u| u.instance == id);
+
+                Self {
+                    id,
+                    uuid: raw::vgpu_uuid(id).ok(),
+                    vm_id: raw::vgpu_vm_id(id).ok(),
+                    vgpu_type,
+                    fb_used: raw::vgpu_fb_usage(id).ok(),
+                    utilisation: utilisation.and_then(|u| u.sm),
+                    memory_utilisation: utilisation.and_then(|u| u.memory),
+                }
+            })
+            .collect())
+    }
+
+    /// How much of its frame buffer the instance is using, in %.
+    pub fn fb_percent(&self) -> Option<f64> {
+        let total = self.vgpu_type.as_ref()?.framebuffer?;
+        let used = self.fb_used?;
+        (total != 0).then(|| used as f64 / total as f64 * 100.0)
+    }
+}
+
+/// The supported types as a list of names, i.e `GRID A100-4C, GRID A100-8C`.
+pub fn type_names(types: &[VgpuType]) -> String {
+    types
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn virtualization_modes() {
+        assert_eq!(VirtualizationMode::from_raw(0), VirtualizationMode::None);
+        assert_eq!(VirtualizationMode::from_raw(3).to_string(), "vGPU host");
+        assert!(VirtualizationMode::from_raw(4).is_host());
+        assert!(!VirtualizationMode::from_raw(2).is_host());
+        assert_eq!(VirtualizationMode::from_raw(9).to_string(), "unknown (9)");
+    }
+
+    #[test]
+    fn framebuffer_usage() {
+        let a100_4c = VgpuType {
+            id: 1,
+            name: "GRID A100-4C".into(),
+            framebuffer: Some(4 * 1_073_741_824),
+        };
+        let mut instance = VgpuInstance {
+            id: 7,
+            uuid: None,
+            vm_id: Some("vm-42".into()),
+            vgpu_type: Some(a100_4c.clone()),
+            fb_used: Some(1_073_741_824),
+            utilisation: None,
+            memory_utilisation: None,
+        };
+        assert_eq!(instance.fb_percent(), Some(25.0));
+
+        instance.vgpu_type = None;
+        assert_eq!(instance.fb_percent(), None);
+
+        let a100_8c = VgpuType {
+            id: 2,
+            name: "GRID A100-8C".into(),
+            framebuffer: None,
+        };
+        assert_eq!(
+            type_names(&[a100_4c, a100_8c]),
+            "GRID A100-4C, GRID A100-8C"
+        );
+    }
+}
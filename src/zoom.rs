@@ -0,0 +1,211 @@
+use ratatui::{prelude::*, symbols::Marker, widgets::*};
+
+use crate::{
+    app::{draw_panel, Frame},
+    config::{Config, Panel},
+    gpu::GpuInfo,
+    sample::{History, Playback, Sample},
+    theme::Theme,
+};
+
+/// A [`Panel`] blown up to fill `area`: the usual widget on top and, for anything with a number
+/// behind it, a chart of its history up to the sample being shown, with the min and max marked.
+/// Memory gets a breakdown of where it's gone and a chart of how busy its bus is alongside.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_zoomed(
+    f: &mut Frame,
+    area: Rect,
+    panel: Panel,
+    gpu: &GpuInfo,
+    history: &History,
+    playback: Playback,
+    info_scroll: &mut u16,
+    cfg: &Config,
+    theme: &Theme,
+) {
+    // Nothing's been read yet, i.e straight after a rescan, the gauges show N/A and the chart's
+    // left empty rather than the panel going blank.
+    let missing = Sample::missing();
+    let sample = playback.sample(history).unwrap_or(&missing);
+
+    if panel == Panel::Misc {
+        draw_panel(f, panel, area, gpu, sample, info_scroll, cfg, theme);
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
+    draw_panel(f, panel, chunks[0], gpu, sample, info_scroll, cfg, theme);
+
+    let chart_area = match panel {
+        Panel::Memory => draw_memory_detail(f, chunks[1], history, sample, cfg, theme),
+        _ => chunks[1],
+    };
+    let points = points(history, sample, |s| value(panel, s, cfg));
+    let top = match panel {
+        Panel::CoreClock => gpu.max_core_clock.map_or(0.0, f64::from),
+        Panel::Memory => cfg.units.memory.convert(sample.memory_total.unwrap_or(0)),
+        Panel::Temperature => cfg.units.temperature.convert(100),
+        _ => 100.0,
+    };
+    let chart = history_chart(panel.title(), unit(panel, cfg), &points, top, theme);
+    f.render_widget(chart, chart_area);
+}
+
+/// Where the memory's gone on top, and memory bandwidth history to the right of what's left,
+/// returns the area left over for the usual chart.
+fn draw_memory_detail(
+    f: &mut Frame,
+    area: Rect,
+    history: &History,
+    sample: &Sample,
+    cfg: &Config,
+    theme: &Theme,
+) -> Rect {
+    let rows = sample.memory_rows(cfg.units.memory);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(rows.len() as u16 + 2),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let lines = rows
+        .into_iter()
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(format!("{label:label_width$}  "), theme.accent_style()),
+                Span::raw(value),
+            ])
+        })
+        .collect::<Vec<_>>();
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Memory Breakdown"),
+        ),
+        chunks[0],
+    );
+
+    let charts = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[1]);
+    let bandwidth = points(history, sample, |s| s.memory_bandwidth.map(f64::from));
+    f.render_widget(
+        history_chart("Memory Bandwidth", "%", &bandwidth, 100.0, theme),
+        charts[1],
+    );
+
+    charts[0]
+}
+
+/// Everything in `history` up to and including `sample`, as (seconds before it, value).
+fn points(
+    history: &History,
+    sample: &Sample,
+    value: impl Fn(&Sample) -> Option<f64>,
+) -> Vec<(f64, f64)> {
+    history
+        .iter()
+        .take_while(|s| s.taken <= sample.taken)
+        .filter_map(|s| {
+            let ago = sample.taken.duration_since(s.taken).as_secs_f64();
+            Some((-ago, value(s)?))
+        })
+        .collect()
+}
+
+fn history_chart<'a>(
+    name: &'a str,
+    unit: &'a str,
+    points: &'a [(f64, f64)],
+    top: f64,
+    theme: &Theme,
+) -> Chart<'a> {
+    let span = points.first().map_or(0.0, |(x, _)| -x).max(1.0);
+    // Never cut off a reading, i.e a clock boosting past its max or a hot card.
+    let top = points.iter().map(|(_, v)| *v).fold(top.max(1.0), f64::max);
+
+    let min = points.iter().min_by(|a, b| a.1.total_cmp(&b.1));
+    let max = points.iter().max_by(|a, b| a.1.total_cmp(&b.1));
+    let avg = points.iter().map(|(_, v)| v).sum::<f64>() / points.len().max(1) as f64;
+
+    let title = match (min, max) {
+        (Some((_, min)), Some((_, max))) => {
+            format!("min {min:.1}{unit} · max {max:.1}{unit} · avg {avg:.1}{unit}")
+        }
+        _ => "no samples yet".into(),
+    };
+
+    let mut datasets = vec![Dataset::default()
+        .name(name)
+        .marker(Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(theme.accent_style())
+        .data(points)];
+    for (name, point, severity) in [("max", max, 3), ("min", min, 0)] {
+        if let Some(point) = point {
+            datasets.push(
+                Dataset::default()
+                    .name(name)
+                    .marker(Marker::Dot)
+                    .graph_type(GraphType::Scatter)
+                    .style(Style::default().fg(theme.severity[severity]))
+                    .data(std::slice::from_ref(point)),
+            );
+        }
+    }
+
+    Chart::new(datasets)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.border_style())
+                .title(Span::styled(title, theme.accent_style())),
+        )
+        .x_axis(
+            Axis::default()
+                .title("seconds")
+                .bounds([-span, 0.0])
+                .labels(
+                    [-span, -span / 2.0, 0.0]
+                        .map(|s| Span::raw(format!("{s:.0}")))
+                        .to_vec(),
+                ),
+        )
+        .y_axis(
+            Axis::default().title(unit).bounds([0.0, top]).labels(
+                [0.0, top / 2.0, top]
+                    .map(|v| Span::raw(format!("{v:.0}")))
+                    .to_vec(),
+            ),
+        )
+}
+
+/// What's charted for `panel`, in the units the panel shows.
+fn value(panel: Panel, sample: &Sample, cfg: &Config) -> Option<f64> {
+    match panel {
+        Panel::CoreUtilisation => sample.utilisation.map(f64::from),
+        Panel::CoreClock => sample.core_clock.map(f64::from),
+        Panel::Memory => sample.memory_used.map(|b| cfg.units.memory.convert(b)),
+        Panel::Temperature => sample.temperature.map(|t| cfg.units.temperature.convert(t)),
+        Panel::Fan => sample.fan_speed,
+        Panel::Misc => None,
+    }
+}
+
+fn unit(panel: Panel, cfg: &Config) -> &'static str {
+    match panel {
+        Panel::CoreUtilisation | Panel::Fan => "%",
+        Panel::CoreClock => "MHz",
+        Panel::Memory => cfg.units.memory.symbol(),
+        Panel::Temperature => cfg.units.temperature.symbol(),
+        Panel::Misc => "",
+    }
+}